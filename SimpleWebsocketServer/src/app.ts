@@ -1,36 +1,227 @@
+import { randomBytes } from 'crypto';
+import { createServer } from 'http';
 import WebSocket, { WebSocketServer } from 'ws';
+import { handleAuthRequest, isAccount, verifyToken } from './auth';
 
 const PORT = process.env.PORT ? parseInt(process.env.PORT) : 8080;
+// File chunk frames start with a 16-byte header: magic, transfer id, index.
+const CHUNK_HEADER_LEN = 16;
+
 interface User {
     ws: WebSocket;
     nick: String;
     isAlive: boolean;
+    // Signed in with a token; the nickname is the account's and can't change.
+    account: boolean;
+    status: Status;
+    statusText?: string;
 }
 
+type Status = 'online' | 'away' | 'busy' | 'invisible';
+const STATUSES: Status[] = ['online', 'away', 'busy', 'invisible'];
+const MAX_STATUS_TEXT = 100;
+// Matches the client's import limits in services/custom_emoji.rs, plus room
+// for the JSON around the images.
+const MAX_PACK_BYTES = 2 * 1024 * 1024 + 64 * 1024;
+const MAX_PACK_ENTRIES = 200;
+
 interface Message {
     messageType: String;
     data: String;
     dataArray: String[];
 }
 
+interface RegisterRequest {
+    nick: string;
+    resumeToken?: string;
+    // Signed token from the auth endpoint; guests leave it out.
+    token?: string;
+}
+
 let users: User[] = [];
 
-console.log(`Listening on port ${PORT}`);
-const wss = new WebSocketServer({ port: PORT });
+// Resume tokens by lowercased nickname. Presenting the current token lets a
+// client take its nickname back from a connection that hasn't closed yet.
+const resumeTokens = new Map<string, string>();
+
+// Older clients send the bare nickname instead of a JSON request.
+const parseRegister = (data: String): RegisterRequest => {
+    try {
+        const request = JSON.parse(data as string);
+        if (request && typeof request.nick === 'string') {
+            return request;
+        }
+    } catch (e) {}
+    return { nick: String(data ?? '') };
+};
+
+// Mirrors `validate` in the client's services/username.rs.
+const MIN_NICK_LEN = 2;
+const MAX_NICK_LEN = 24;
+const NICK_PATTERN = new RegExp('^[\\p{L}\\p{N}][\\p{L}\\p{N}\\p{M}_.-]*$', 'u');
+
+const normalizeNick = (nick: string) => nick.trim().normalize('NFKC');
+
+const nickError = (nick: string): string | null => {
+    const length = Array.from(nick).length;
+    if (length < MIN_NICK_LEN || length > MAX_NICK_LEN) {
+        return `Usernames must be ${MIN_NICK_LEN} to ${MAX_NICK_LEN} characters long`;
+    }
+    if (!NICK_PATTERN.test(nick)) {
+        return 'Usernames may only contain letters, digits, _, - and .';
+    }
+    return null;
+};
+
+const isTaken = (nick: string, except?: WebSocket) =>
+    users.some((u) => u.ws !== except && u.nick.toLowerCase() === nick.toLowerCase());
+
+// Free variants like `alice_2`, trimming the base so they stay within the limit.
+const suggestNicks = (nick: string, count = 3): string[] => {
+    const suggestions: string[] = [];
+    for (let i = 2; suggestions.length < count && i < 1000; i++) {
+        const suffix = `_${i}`;
+        const base = Array.from(nick).slice(0, MAX_NICK_LEN - suffix.length).join('');
+        const candidate = base + suffix;
+        if (!isTaken(candidate)) {
+            suggestions.push(candidate);
+        }
+    }
+    return suggestions;
+};
+
+// Plain HTTP requests go to the stand-in auth endpoint, upgrades to the chat.
+const server = createServer(handleAuthRequest);
+const wss = new WebSocketServer({ server });
+server.listen(PORT, () => console.log(`Listening on port ${PORT}`));
 
 wss.on('connection', (ws: WebSocket) => {
     console.log('ws connected');
 
-    ws.on('message', (data) => {
+    ws.on('message', (data, isBinary) => {
+        if (isBinary) {
+            // File chunks are opaque to the server; relay them to everyone else
+            // and echo the header back so the sender can count it as delivered.
+            if (users.some((u) => u.ws === ws)) {
+                broadcast(data, ws);
+                if (Buffer.isBuffer(data) && data.length >= CHUNK_HEADER_LEN) {
+                    ws.send(data.subarray(0, CHUNK_HEADER_LEN));
+                }
+            }
+            return;
+        }
         const raw_data = data.toString();
         try {
             const parsed_data: Message = JSON.parse(raw_data);
             switch (parsed_data.messageType) {
                 case 'register':
-                    users.push({ ws, nick: parsed_data.data, isAlive: true });
-                    broadcast(JSON.stringify({ messageType: 'users', dataArray: users.map((u) => u.nick) }));
+                    const request = parseRegister(parsed_data.data);
+                    const claims = request.token ? verifyToken(request.token) : null;
+                    if (request.token && !claims) {
+                        ws.send(
+                            JSON.stringify({
+                                messageType: 'registered',
+                                data: JSON.stringify({
+                                    accepted: false,
+                                    nick: request.nick,
+                                    error: 'Your sign-in has expired; please sign in again',
+                                }),
+                            })
+                        );
+                        break;
+                    }
+                    // A signed-in user always gets the account's own name.
+                    const nick = claims ? claims.sub : normalizeNick(request.nick);
+                    const key = nick.toLowerCase();
+                    const validResume = !!request.resumeToken && resumeTokens.get(key) === request.resumeToken;
+                    const resuming = !!claims || validResume;
+                    if (resuming) {
+                        users
+                            .filter((u) => u.ws !== ws && u.nick.toLowerCase() === key)
+                            .forEach((u) => u.ws.terminate());
+                        users = users.filter((u) => u.ws === ws || u.nick.toLowerCase() !== key);
+                    }
+                    const invalid = nickError(nick);
+                    const reserved = !claims && isAccount(nick);
+                    if (invalid || reserved || isTaken(nick, ws)) {
+                        ws.send(
+                            JSON.stringify({
+                                messageType: 'registered',
+                                data: JSON.stringify({
+                                    accepted: false,
+                                    nick,
+                                    error:
+                                        invalid ??
+                                        (reserved
+                                            ? `${nick} belongs to a registered account; sign in to use it`
+                                            : `${nick} is already taken`),
+                                    suggestions: invalid ? [] : suggestNicks(nick),
+                                }),
+                            })
+                        );
+                        break;
+                    }
+                    users = users.filter((u) => u.ws !== ws);
+                    users.push({ ws, nick, isAlive: true, account: !!claims, status: 'online' });
+                    const resumeToken = validResume ? request.resumeToken! : randomBytes(24).toString('hex');
+                    resumeTokens.set(key, resumeToken);
+                    ws.send(
+                        JSON.stringify({
+                            messageType: 'registered',
+                            data: JSON.stringify({ accepted: true, nick, resumeToken }),
+                        })
+                    );
+                    broadcastUsers();
+                    break;
+                case 'rename':
+                    const renamer = users.find((u) => u.ws === ws);
+                    if (!renamer) {
+                        break;
+                    }
+                    const from = renamer.nick;
+                    const to = normalizeNick(String(parsed_data.data ?? ''));
+                    const refusal = renamer.account
+                        ? 'Signed-in users keep their account name'
+                        : nickError(to) ??
+                          (isAccount(to) ? `${to} belongs to a registered account` : null) ??
+                          (isTaken(to, ws) ? `${to} is already taken` : null);
+                    if (refusal) {
+                        ws.send(
+                            JSON.stringify({
+                                messageType: 'renamed',
+                                data: JSON.stringify({ from, to, error: refusal }),
+                            })
+                        );
+                        break;
+                    }
+                    renamer.nick = to;
+                    // The resume token follows the user to the new name.
+                    const renamedToken = resumeTokens.get(from.toLowerCase());
+                    resumeTokens.delete(from.toLowerCase());
+                    if (renamedToken) {
+                        resumeTokens.set(to.toLowerCase(), renamedToken);
+                    }
+                    broadcast(JSON.stringify({ messageType: 'renamed', data: JSON.stringify({ from, to }) }));
+                    broadcastUsers();
+                    break;
+                case 'presence':
+                    const member = users.find((u) => u.ws === ws);
+                    if (member) {
+                        const presence = JSON.parse(parsed_data.data as string);
+                        member.status = STATUSES.includes(presence.status) ? presence.status : 'online';
+                        member.statusText =
+                            typeof presence.text === 'string' && presence.text.trim()
+                                ? Array.from(presence.text.trim()).slice(0, MAX_STATUS_TEXT).join('')
+                                : undefined;
+                        broadcastUsers();
+                    }
+                    break;
+                case 'leave':
+                    users = users.filter((u) => u.ws !== ws);
+                    broadcastUsers();
                     break;
                 case 'message':
+                case 'emote':
                     const sender = users.find((u) => u.ws === ws);
                     if (sender) {
                         broadcast(
@@ -39,16 +230,78 @@ wss.on('connection', (ws: WebSocket) => {
                                 data: JSON.stringify({
                                     from: sender.nick,
                                     message: parsed_data.data,
+                                    emote: parsed_data.messageType === 'emote',
                                     time: Date.now(),
                                 }),
                             })
                         );
                     }
+                    break;
+                case 'direct':
+                    const author = users.find((u) => u.ws === ws);
+                    if (author) {
+                        const direct = JSON.parse(parsed_data.data as string);
+                        const payload = JSON.stringify({
+                            messageType: 'message',
+                            data: JSON.stringify({
+                                from: author.nick,
+                                to: direct.to,
+                                message: direct.message,
+                                time: Date.now(),
+                            }),
+                        });
+                        users
+                            .filter((u) => u.ws === ws || u.nick === direct.to)
+                            .forEach((u) => u.ws.send(payload));
+                    }
+                    break;
+                case 'emojipack':
+                    const sharer = users.find((u) => u.ws === ws);
+                    const packData = String(parsed_data.data ?? '');
+                    if (sharer && Buffer.byteLength(packData) <= MAX_PACK_BYTES) {
+                        const pack = JSON.parse(packData);
+                        const entries =
+                            Object.keys(pack?.emoji ?? {}).length + Object.keys(pack?.stickers ?? {}).length;
+                        if (entries > MAX_PACK_ENTRIES) {
+                            break;
+                        }
+                        broadcast(
+                            JSON.stringify({
+                                messageType: 'emojipack',
+                                data: JSON.stringify({ from: sharer.nick, pack }),
+                            })
+                        );
+                    }
+                    break;
+                case 'file':
+                case 'filecontrol':
+                    const owner = users.find((u) => u.ws === ws);
+                    if (owner) {
+                        const payload = JSON.parse(parsed_data.data as string);
+                        if (parsed_data.messageType === 'file') {
+                            payload.from = owner.nick;
+                        }
+                        broadcast(
+                            JSON.stringify({
+                                messageType: parsed_data.messageType,
+                                data: JSON.stringify(payload),
+                            })
+                        );
+                    }
+                    break;
             }
         } catch (e) {
             console.log('Error in message', e);
         }
     });
+
+    // Free the nickname right away so a reconnecting client can reclaim it.
+    ws.on('close', () => {
+        if (users.some((u) => u.ws === ws)) {
+            users = users.filter((u) => u.ws !== ws);
+            broadcastUsers();
+        }
+    });
 });
 
 const interval = setInterval(function ping() {
@@ -56,13 +309,27 @@ const interval = setInterval(function ping() {
     const updated_users = users.filter((u) => current_clients.includes(u.ws));
     if (updated_users.length !== users.length) {
         users = updated_users;
-        broadcast(JSON.stringify({ messageType: 'users', dataArray: users.map((u) => u.nick) }));
+        broadcastUsers();
     }
 }, 5000);
 
-const broadcast = (data: any) => {
+// Everyone gets the user list with presence, minus invisible users other
+// than themselves.
+const broadcastUsers = () => {
+    wss.clients.forEach((client) => {
+        if (client.readyState !== WebSocket.OPEN) {
+            return;
+        }
+        const visible = users
+            .filter((u) => u.status !== 'invisible' || u.ws === client)
+            .map((u) => ({ name: u.nick, status: u.status, text: u.statusText, account: u.account }));
+        client.send(JSON.stringify({ messageType: 'users', dataArray: visible }));
+    });
+};
+
+const broadcast = (data: any, except?: WebSocket) => {
     wss.clients.forEach((client) => {
-        if (client.readyState === WebSocket.OPEN) {
+        if (client !== except && client.readyState === WebSocket.OPEN) {
             client.send(data);
         }
     });
@@ -0,0 +1,142 @@
+// Stand-in authentication endpoint for local development and testing.
+//
+// Accounts come from AUTH_USERS ("alice:secret,bob:hunter2"); one-time codes
+// are printed to the console instead of being emailed. Tokens are HMAC-signed
+// with AUTH_SECRET, or a random secret that changes on every restart.
+import { createHmac, randomBytes, randomInt, timingSafeEqual } from 'crypto';
+import { IncomingMessage, ServerResponse } from 'http';
+
+const SECRET = process.env.AUTH_SECRET || randomBytes(32).toString('hex');
+const TOKEN_TTL_MS = (process.env.AUTH_TOKEN_TTL ? parseInt(process.env.AUTH_TOKEN_TTL) : 15 * 60) * 1000;
+const CODE_TTL_MS = 5 * 60 * 1000;
+// Wrong guesses allowed per code before it is thrown away.
+const MAX_CODE_ATTEMPTS = 5;
+
+interface Account {
+    name: string;
+    password: string;
+}
+
+const accounts = new Map<string, Account>(
+    (process.env.AUTH_USERS || 'demo:demo')
+        .split(',')
+        .map((entry) => entry.split(':'))
+        .filter(([name, password]) => name && password)
+        .map(([name, password]): [string, Account] => [name.trim().toLowerCase(), { name: name.trim(), password }])
+);
+
+const codes = new Map<string, { code: string; expires: number; attempts: number }>();
+
+interface TokenClaims {
+    sub: string;
+    exp: number;
+}
+
+const sign = (payload: string) => createHmac('sha256', SECRET).update(payload).digest('base64url');
+
+const issueToken = (name: string) => {
+    const claims: TokenClaims = { sub: name, exp: Date.now() + TOKEN_TTL_MS };
+    const payload = Buffer.from(JSON.stringify(claims)).toString('base64url');
+    return { token: `${payload}.${sign(payload)}`, expiresAt: claims.exp };
+};
+
+// Returns the claims of a well-signed, unexpired token.
+export const verifyToken = (token: string): TokenClaims | null => {
+    const [payload, signature] = token.split('.');
+    if (!payload || !signature) {
+        return null;
+    }
+    const expected = Buffer.from(sign(payload));
+    const actual = Buffer.from(signature);
+    if (expected.length !== actual.length || !timingSafeEqual(expected, actual)) {
+        return null;
+    }
+    try {
+        const claims: TokenClaims = JSON.parse(Buffer.from(payload, 'base64url').toString());
+        return claims.exp > Date.now() ? claims : null;
+    } catch (e) {
+        return null;
+    }
+};
+
+// Whether `nick` belongs to an account, so guests can't take it.
+export const isAccount = (nick: string) => accounts.has(nick.toLowerCase());
+
+const send = (res: ServerResponse, status: number, body: object) => {
+    res.writeHead(status, { 'Content-Type': 'application/json' });
+    res.end(JSON.stringify(body));
+};
+
+const readJson = (req: IncomingMessage): Promise<any> =>
+    new Promise((resolve) => {
+        let body = '';
+        req.on('data', (chunk) => (body += chunk));
+        req.on('end', () => {
+            try {
+                resolve(JSON.parse(body || '{}'));
+            } catch (e) {
+                resolve({});
+            }
+        });
+    });
+
+export const handleAuthRequest = async (req: IncomingMessage, res: ServerResponse) => {
+    // The client is served from another port during development.
+    res.setHeader('Access-Control-Allow-Origin', '*');
+    res.setHeader('Access-Control-Allow-Headers', 'Content-Type, Authorization');
+    res.setHeader('Access-Control-Allow-Methods', 'POST, OPTIONS');
+    if (req.method === 'OPTIONS') {
+        res.writeHead(204);
+        res.end();
+        return;
+    }
+    if (req.method !== 'POST') {
+        send(res, 404, { error: 'Not found' });
+        return;
+    }
+
+    const body = await readJson(req);
+    const account = accounts.get(String(body.username ?? '').trim().toLowerCase());
+
+    switch (req.url) {
+        case '/auth/password':
+            if (!account || account.password !== body.password) {
+                send(res, 401, { error: 'Wrong username or password' });
+                return;
+            }
+            send(res, 200, issueToken(account.name));
+            return;
+        case '/auth/code/request':
+            if (account) {
+                const code = randomInt(0, 1_000_000).toString().padStart(6, '0');
+                codes.set(account.name, { code, expires: Date.now() + CODE_TTL_MS, attempts: 0 });
+                console.log(`One-time code for ${account.name}: ${code}`);
+            }
+            // Same answer either way, so this can't be used to probe for accounts.
+            send(res, 200, { sent: true });
+            return;
+        case '/auth/code/verify':
+            const pending = account && codes.get(account.name);
+            if (!account || !pending || pending.expires < Date.now() || pending.code !== String(body.code ?? '').trim()) {
+                if (account && pending && ++pending.attempts >= MAX_CODE_ATTEMPTS) {
+                    codes.delete(account.name);
+                }
+                send(res, 401, { error: 'That code is wrong or has expired' });
+                return;
+            }
+            codes.delete(account.name);
+            send(res, 200, issueToken(account.name));
+            return;
+        case '/auth/refresh':
+            const bearer = (req.headers.authorization ?? '').replace(/^Bearer /, '');
+            const claims = verifyToken(bearer);
+            if (!claims) {
+                send(res, 401, { error: 'Your sign-in has expired' });
+                return;
+            }
+            send(res, 200, issueToken(claims.sub));
+            return;
+        default:
+            send(res, 404, { error: 'Not found' });
+    }
+};
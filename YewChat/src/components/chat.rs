@@ -1,23 +1,175 @@
+use std::collections::HashMap;
+
+use futures::SinkExt;
+use gloo::events::EventListener;
+use gloo::file::File;
+use gloo::timers::callback::{Interval, Timeout};
 use serde::{Deserialize, Serialize};
-use web_sys::HtmlInputElement;
+use wasm_bindgen::JsCast;
+use wasm_bindgen_futures::spawn_local;
+use web_sys::{ClipboardEvent, FileList, HtmlInputElement, NotificationPermission};
 use yew::prelude::*;
 use yew_agent::{Bridge, Bridged};
 use yew_router::prelude::*;
 
-use crate::services::event_bus::EventBus;
-use crate::{services::websocket::WebsocketService, User, Route};
+use crate::components::drawer::{Drawer, DrawerToggle};
+use crate::components::emoji_picker::EmojiPicker;
+use crate::components::file_card::FileCard;
+use crate::components::lightbox::Lightbox;
+use crate::components::media_gallery::MediaGallery;
+use crate::components::profile_card::ProfileCard;
+use crate::components::settings::SettingsPanel;
+use crate::components::status_menu::StatusMenu;
+use crate::components::user_list::{self, UserList, UserListEntry};
+use crate::services::auth::{self, AuthToken};
+use crate::services::avatar::avatar_url;
+use crate::services::commands::{self, CommandAction, Parsed};
+use crate::services::custom_emoji::{self, EmojiPack, Segment, SharedPack};
+use crate::services::emoji::{self, SkinTone};
+use crate::services::event_bus::{BusMessage, EventBus};
+use crate::services::file_transfer::{
+    self, FileControl, FileOffer, IncomingTransfer, OutgoingTransfer, TransferState,
+    MAX_FILE_SIZE,
+};
+use crate::services::gestures::{self, Swipe, SwipeTracker};
+use crate::services::history::{MessageHistory, StoredMessage};
+use crate::services::image::{self, PreparedImage};
+use crate::services::last_seen::{self, LastSeen};
+use crate::services::media::{media_kind, MediaItem, MediaKind};
+use crate::services::notifications::{self, DesktopNotification, Trigger};
+use crate::services::presence::{IdleWatcher, OnlineUser, Presence, Status};
+use crate::services::roster::{RosterEvent, RosterTracker};
+use crate::services::session::{self, RedirectTarget};
+use crate::services::settings::Settings;
+use crate::services::sounds::{SoundEvent, SoundPlayer};
+use crate::services::username::{self, RegisterRequest, Registration, Rename};
+use crate::services::store::{self, Action, Connection, Subscription};
+use crate::services::unread::UnreadTracker;
+use crate::services::viewport::{self, KeyboardWatcher};
+use crate::{services::websocket::WebsocketService, Route};
+
+const RECONNECT_DELAY_MS: u32 = 2_000;
+const CLOCK_TICK_MS: u32 = 60_000;
+const HIGHLIGHT_MS: u32 = 3_000;
+const MAX_NOTIFICATIONS: usize = 5;
+const MAX_PACK_OFFERS: usize = 3;
 
 pub enum Msg {
     HandleMsg(String),
+    HandleBytes(Vec<u8>),
+    ConnectionClosed,
+    Reconnect,
     SubmitMessage,
     ToggleEmojiPicker,
     InsertEmoji(String),
+    ToggleSettings,
+    UpdateSettings(Settings),
+    SelectSidebarTab(SidebarTab),
+    OpenLightbox(usize),
+    CloseLightbox,
+    PickFile,
+    FilesSelected,
+    FileLoaded(Result<OutgoingTransfer, String>),
+    CancelTransfer(u64),
+    AttachFiles(Vec<File>),
+    ImagePrepared(Result<PreparedImage, String>),
+    RemoveAttachment(usize),
+    SetDragging(bool),
+    ComposerInput(String),
+    CompleteCommand(Option<String>),
+    CompleteShortcode(Option<String>),
+    SetSkinTone(SkinTone),
+    SendSticker(String),
+    ImportPack(File),
+    PackLoaded(Result<EmojiPack, String>),
+    AcceptPack(usize),
+    DeclinePack(usize),
+    SharePack(String),
+    RemovePack(String),
+    UsernameChanged(String),
+    SettingsChanged(Settings),
+    ConnectionChanged(Connection),
+    RoomChanged(Option<String>),
+    Rename(String),
+    ToggleStatusMenu,
+    SetPresence(Presence),
+    IdleChanged(bool),
+    FlushRoster,
+    Tick,
+    StartDirect(String),
+    ShowProfile(Option<String>),
+    Mention(String),
+    ToggleIgnore(String),
+    ToggleDrawer,
+    TouchStart((i32, i32)),
+    TouchEnd((i32, i32)),
+    KeyboardResized(Option<f64>),
+    RequestNotifications,
+    NotificationPermissionChanged(NotificationPermission),
+    DismissNotificationPrompt,
+    JumpToMessage(u64),
+    ClearHighlight,
+    WindowFocused,
+    PreviewSound((SoundEvent, f32)),
+    SetDnd(bool),
+    Logout,
+    TokenRefreshed(Result<AuthToken, String>),
+}
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum SidebarTab {
+    Users,
+    Media,
 }
 
 #[derive(Deserialize)]
 struct MessageData {
     from: String,
     message: String,
+    #[serde(default)]
+    to: Option<String>,
+    /// Sent with `/me`, rendered as "* alice waves".
+    #[serde(default)]
+    emote: bool,
+    /// Set by `Chat::push_message`. Unlike the index it isn't reused after `/clear`.
+    #[serde(skip)]
+    id: u64,
+    #[serde(skip)]
+    kind: EntryKind,
+    /// The author's nickname when they sent this, if they have since been
+    /// renamed; `from` always holds their current name.
+    #[serde(skip)]
+    sent_as: Option<String>,
+}
+
+impl MessageData {
+    fn system(message: String) -> Self {
+        Self {
+            from: String::new(),
+            message,
+            to: None,
+            emote: false,
+            sent_as: None,
+            id: 0,
+            kind: EntryKind::System,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+enum EntryKind {
+    #[default]
+    Chat,
+    File(u64),
+    System,
+    /// Someone joining or leaving; hidden unless `show_join_leave` is set.
+    Roster,
+}
+
+#[derive(Serialize)]
+struct DirectMessage {
+    to: String,
+    message: String,
 }
 
 #[derive(Debug, Deserialize, Serialize)]
@@ -26,20 +178,31 @@ pub enum MsgTypes {
     Users,
     Register,
     Message,
+    File,
+    FileControl,
+    Direct,
+    Emote,
+    EmojiPack,
+    Leave,
+    Registered,
+    Rename,
+    Renamed,
+    Presence,
 }
 
 #[derive(Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 struct WebSocketMessage {
     message_type: MsgTypes,
-    data_array: Option<Vec<String>>,
+    data_array: Option<Vec<OnlineUser>>,
     data: Option<String>,
 }
 
 #[derive(Clone)]
 struct UserProfile {
     name: String,
-    avatar: String,
+    presence: Presence,
+    account: bool,
 }
 
 pub struct Chat {
@@ -49,6 +212,61 @@ pub struct Chat {
     wss: WebsocketService,
     messages: Vec<MessageData>,
     show_emoji_picker: bool,
+    settings: Settings,
+    show_settings: bool,
+    sidebar_tab: SidebarTab,
+    lightbox: Option<usize>,
+    file_input: NodeRef,
+    outgoing: HashMap<u64, OutgoingTransfer>,
+    incoming: HashMap<u64, IncomingTransfer>,
+    composer_error: Option<String>,
+    attachments: Vec<PreparedImage>,
+    preparing_images: usize,
+    dragging: bool,
+    composer_text: String,
+    recent_emoji: Vec<String>,
+    emoji_packs: Vec<EmojiPack>,
+    pack_offers: Vec<SharedPack>,
+    /// Set once the user logs out, so the closing socket isn't reopened.
+    leaving: bool,
+    /// Online nicknames that look like someone who joined earlier, mapped
+    /// to the name they resemble.
+    lookalikes: HashMap<String, String>,
+    remembered: bool,
+    /// Renews the sign-in token shortly before it expires.
+    refresh_timer: Option<Timeout>,
+    rename_error: Option<String>,
+    /// The status the user picked, before auto-away is applied.
+    presence: Presence,
+    idle: bool,
+    idle_watcher: Option<IdleWatcher>,
+    show_status_menu: bool,
+    roster: RosterTracker,
+    roster_timer: Option<Timeout>,
+    last_seen: LastSeen,
+    history: MessageHistory,
+    profile: Option<String>,
+    show_drawer: bool,
+    swipe: SwipeTracker,
+    keyboard_height: Option<f64>,
+    _keyboard: KeyboardWatcher,
+    notifications: Vec<DesktopNotification>,
+    notification_permission: NotificationPermission,
+    highlighted: Option<u64>,
+    next_message_id: u64,
+    highlight_timer: Option<Timeout>,
+    _focus_listener: Option<EventListener>,
+    unread: UnreadTracker,
+    sounds: SoundPlayer,
+    dnd_active: bool,
+    missed_mentions: u32,
+    now: f64,
+    _clock: Interval,
+    // Slices of the app store, kept current by `_subscriptions`.
+    username: String,
+    connection: Connection,
+    active_room: Option<String>,
+    _subscriptions: Vec<Subscription>,
 }
 
 impl Component for Chat {
@@ -56,90 +274,406 @@ impl Component for Chat {
     type Properties = ();
 
     fn create(ctx: &Context<Self>) -> Self {
-        let (user, _) = ctx
-            .link()
-            .context::<User>(Callback::noop())
-            .expect("context to be set");
         let wss = WebsocketService::new();
-        let username = user.username.borrow().clone();
+        Self::register(&wss);
 
-        let message = WebSocketMessage {
-            message_type: MsgTypes::Register,
-            data: Some(username.to_string()),
-            data_array: None,
-        };
-
-        if let Ok(_) = wss
-            .tx
-            .clone()
-            .try_send(serde_json::to_string(&message).unwrap())
-        {
-            log::debug!("message sent successfully");
+        if let Some(location) = ctx.link().location() {
+            let query: Vec<(String, String)> = location.query().unwrap_or_default();
+            let room = query
+                .iter()
+                .find(|(key, _)| key == "room")
+                .map(|(_, room)| room.clone());
+            store::dispatch(Action::OpenRoom(room));
+            session::update_remembered(|identity| {
+                identity.last_location = Some(RedirectTarget {
+                    path: location.pathname(),
+                    query,
+                })
+            });
         }
 
-        Self {
+        let state = store::state();
+        let link = ctx.link();
+        let subscriptions = vec![
+            store::subscribe(|s| s.user.username.clone(), link.callback(Msg::UsernameChanged)),
+            store::subscribe(|s| s.settings.clone(), link.callback(Msg::SettingsChanged)),
+            store::subscribe(|s| s.connection, link.callback(Msg::ConnectionChanged)),
+            store::subscribe(|s| s.rooms.active.clone(), link.callback(Msg::RoomChanged)),
+        ];
+
+        let mut chat = Self {
             users: vec![],
             messages: vec![],
             chat_input: NodeRef::default(),
             wss,
-            _producer: EventBus::bridge(ctx.link().callback(Msg::HandleMsg)),
+            _producer: EventBus::bridge(ctx.link().callback(|msg| match msg {
+                BusMessage::Text(s) => Msg::HandleMsg(s),
+                BusMessage::Bytes(b) => Msg::HandleBytes(b),
+                BusMessage::Closed => Msg::ConnectionClosed,
+            })),
             show_emoji_picker: false,
-        }
+            settings: state.settings.clone(),
+            show_settings: false,
+            sidebar_tab: SidebarTab::Users,
+            lightbox: None,
+            file_input: NodeRef::default(),
+            outgoing: HashMap::new(),
+            incoming: HashMap::new(),
+            composer_error: None,
+            attachments: vec![],
+            preparing_images: 0,
+            dragging: false,
+            composer_text: String::new(),
+            recent_emoji: emoji::load_recents(),
+            emoji_packs: custom_emoji::load_packs(),
+            pack_offers: vec![],
+            leaving: false,
+            lookalikes: HashMap::new(),
+            remembered: session::remembered().is_some(),
+            refresh_timer: None,
+            rename_error: None,
+            presence: Presence::default(),
+            idle: false,
+            idle_watcher: None,
+            show_status_menu: false,
+            roster: RosterTracker::default(),
+            roster_timer: None,
+            last_seen: LastSeen::load(),
+            history: MessageHistory::load(),
+            profile: None,
+            show_drawer: false,
+            swipe: SwipeTracker::default(),
+            keyboard_height: None,
+            _keyboard: KeyboardWatcher::new(ctx.link().callback(Msg::KeyboardResized)),
+            notifications: vec![],
+            notification_permission: notifications::permission(),
+            highlighted: None,
+            next_message_id: 0,
+            highlight_timer: None,
+            _focus_listener: web_sys::window().map(|window| {
+                let link = ctx.link().clone();
+                EventListener::new(&window, "focus", move |_| link.send_message(Msg::WindowFocused))
+            }),
+            unread: UnreadTracker::new(),
+            sounds: SoundPlayer::default(),
+            dnd_active: state.settings.dnd.is_active(),
+            missed_mentions: 0,
+            now: js_sys::Date::now(),
+            _clock: {
+                let link = ctx.link().clone();
+                Interval::new(CLOCK_TICK_MS, move || link.send_message(Msg::Tick))
+            },
+            username: state.user.username.clone(),
+            connection: state.connection,
+            active_room: state.rooms.active.clone(),
+            _subscriptions: subscriptions,
+        };
+        chat.schedule_token_refresh(ctx);
+        chat.watch_idle(ctx);
+        chat
     }
 
-    fn update(&mut self, _ctx: &Context<Self>, msg: Self::Message) -> bool {
+    fn update(&mut self, ctx: &Context<Self>, msg: Self::Message) -> bool {
         match msg {
             Msg::HandleMsg(s) => {
                 let msg: WebSocketMessage = serde_json::from_str(&s).unwrap();
                 match msg.message_type {
                     MsgTypes::Users => {
                         let users_from_message = msg.data_array.unwrap_or_default();
+                        let names: Vec<String> =
+                            users_from_message.iter().map(|u| u.name.clone()).collect();
+                        self.lookalikes = username::lookalikes(&names);
+                        let now = js_sys::Date::now();
+                        // Whoever just dropped out was last here now.
+                        for name in self.users.iter().map(|u| &u.name).chain(&names) {
+                            self.last_seen.touch(name, now);
+                        }
+                        self.last_seen.save();
+                        self.now = now;
+                        let events = self.roster.update(&names, now);
+                        self.push_roster_events(events);
+                        self.schedule_roster_flush(ctx);
                         self.users = users_from_message
-                            .iter()
+                            .into_iter()
                             .map(|u| UserProfile {
-                                name: u.into(),
-                                avatar: format!(
-                                    "https://avatars.dicebear.com/api/adventurer-neutral/{}.svg",
-                                    u
-                                )
-                                .into(),
+                                name: u.name,
+                                presence: u.presence,
+                                account: u.account,
                             })
                             .collect();
-                        return true;
+                        true
                     }
                     MsgTypes::Message => {
                         let message_data: MessageData =
                             serde_json::from_str(&msg.data.unwrap()).unwrap();
-                        self.messages.push(message_data);
+                        let now = js_sys::Date::now();
+                        self.last_seen.touch(&message_data.from, now);
+                        self.last_seen.save();
+                        self.history.push(StoredMessage {
+                            from: message_data.from.clone(),
+                            to: message_data.to.clone(),
+                            message: message_data.message.clone(),
+                            emote: message_data.emote,
+                            time: now,
+                        });
+                        self.history.save();
+                        if message_data.from != self.username
+                            && !self.settings.is_ignored(&message_data.from)
+                        {
+                            let direct = message_data.to.as_deref() == Some(self.username.as_str());
+                            let mentioned = notifications::mentions(&message_data.message, &self.username);
+                            self.unread.record(direct || mentioned);
+                            let muted = self.dnd_active
+                                && !self.settings.dnd.allows(&message_data.from, direct);
+                            if muted && (direct || mentioned) {
+                                self.missed_mentions += 1;
+                            }
+                            let cue = if direct {
+                                SoundEvent::Direct
+                            } else if mentioned {
+                                SoundEvent::Mention
+                            } else {
+                                SoundEvent::Message
+                            };
+                            if !muted {
+                                self.sounds.play(cue, &self.settings.sounds);
+                            }
+                        }
+                        self.push_message(message_data);
+                        self.notify(ctx, self.messages.len() - 1);
+                        true
+                    }
+                    MsgTypes::File => {
+                        let offer: FileOffer = match msg
+                            .data
+                            .as_deref()
+                            .and_then(|d| serde_json::from_str(d).ok())
+                        {
+                            Some(offer) => offer,
+                            None => return false,
+                        };
+                        let id = offer.id;
+                        if self.outgoing.contains_key(&id) {
+                            // Our own offer came back, so everyone has seen it
+                            // and the chunks can follow.
+                            self.stream_chunks(id, 0);
+                        } else {
+                            self.incoming.insert(id, IncomingTransfer::new(offer.clone()));
+                        }
+                        self.push_message(MessageData {
+                            from: offer.from,
+                            message: offer.name,
+                            to: None,
+                            emote: false,
+                            id: 0,
+                            kind: EntryKind::File(id),
+                            sent_as: None,
+                        });
+                        true
+                    }
+                    MsgTypes::FileControl => {
+                        let control: FileControl = match msg
+                            .data
+                            .as_deref()
+                            .and_then(|d| serde_json::from_str(d).ok())
+                        {
+                            Some(control) => control,
+                            None => return false,
+                        };
+                        match control {
+                            FileControl::Cancel { id } => match self.incoming.get_mut(&id) {
+                                Some(transfer) => {
+                                    transfer.cancel();
+                                    true
+                                }
+                                None => false,
+                            },
+                            FileControl::Resume { id, from_index } => {
+                                self.stream_chunks(id, from_index);
+                                false
+                            }
+                        }
+                    }
+                    MsgTypes::Registered => {
+                        let registration: Registration = match msg
+                            .data
+                            .as_deref()
+                            .and_then(|d| serde_json::from_str(d).ok())
+                        {
+                            Some(registration) => registration,
+                            None => return false,
+                        };
+                        if registration.accepted {
+                            session::update_remembered(|identity| {
+                                identity.username = registration.nick.clone();
+                                identity.resume_token = registration.resume_token.clone();
+                            });
+                            if self.connection == Connection::Reconnecting && !self.dnd_active {
+                                self.sounds.play(SoundEvent::Connect, &self.settings.sounds);
+                            }
+                            // The server may have normalized the nickname.
+                            store::dispatch(Action::SetUsername(registration.nick));
+                            store::dispatch(Action::SetConnection(Connection::Connected));
+                            // The server forgets presence with the connection.
+                            self.send_presence();
+                            return false;
+                        }
+
+                        // Let the login page explain and offer alternatives,
+                        // then bring the user back here once they pick one.
+                        self.leaving = true;
+                        self.wss.close();
+                        if let Some(location) = ctx.link().location() {
+                            session::save_redirect(&RedirectTarget {
+                                path: location.pathname(),
+                                query: location.query().unwrap_or_default(),
+                            });
+                        }
+                        session::save_rejection(&registration);
+                        // Otherwise the login page would sign straight back in
+                        // with the refused name.
+                        session::forget();
+                        store::dispatch(Action::SignOut);
+                        ctx.link().history().unwrap().push(Route::Login);
+                        false
+                    }
+                    MsgTypes::Renamed => {
+                        let rename: Rename = match msg
+                            .data
+                            .as_deref()
+                            .and_then(|d| serde_json::from_str(d).ok())
+                        {
+                            Some(rename) => rename,
+                            None => return false,
+                        };
+                        if let Some(error) = rename.error {
+                            self.rename_error = Some(error.clone());
+                            self.composer_error = Some(error);
+                            return true;
+                        }
+                        self.apply_rename(&rename.from, &rename.to);
+                        true
+                    }
+                    MsgTypes::EmojiPack => {
+                        let shared: SharedPack = match msg
+                            .data
+                            .as_deref()
+                            .and_then(|d| serde_json::from_str(d).ok())
+                        {
+                            Some(shared) => shared,
+                            None => return false,
+                        };
+                        if let Err(e) = custom_emoji::validate(&shared.pack) {
+                            log::error!("rejected emoji pack from {}: {}", shared.from, e);
+                            return false;
+                        }
+                        if shared.from == self.username {
+                            return false;
+                        }
+                        self.pack_offers
+                            .retain(|offer| offer.from != shared.from || offer.pack.name != shared.pack.name);
+                        self.pack_offers.push(shared);
+                        if self.pack_offers.len() > MAX_PACK_OFFERS {
+                            self.pack_offers.remove(0);
+                        }
+                        true
+                    }
+                    _ => false,
+                }
+            }
+            Msg::HandleBytes(frame) => {
+                if let Some((id, index, payload)) = file_transfer::decode_chunk(&frame) {
+                    if let Some(transfer) = self.incoming.get_mut(&id) {
+                        transfer.accept_chunk(index, payload);
                         return true;
                     }
-                    _ => {
-                        return false;
+                    // The server echoes the header of each of our chunks it relayed.
+                    if let Some(transfer) = self.outgoing.get_mut(&id) {
+                        transfer.acknowledge(index);
+                        return true;
+                    }
+                }
+                false
+            }
+            Msg::ConnectionClosed if self.leaving => false,
+            Msg::ConnectionClosed => {
+                // Only for the first drop, not every failed reconnect.
+                if self.connection == Connection::Connected && !self.dnd_active {
+                    self.sounds.play(SoundEvent::Disconnect, &self.settings.sounds);
+                }
+                store::dispatch(Action::SetConnection(Connection::Reconnecting));
+                self.roster.reset();
+                for transfer in self.outgoing.values_mut() {
+                    transfer.cursor.stop();
+                    if transfer.state == TransferState::InProgress {
+                        transfer.state = TransferState::Paused;
                     }
                 }
+                for transfer in self.incoming.values_mut() {
+                    transfer.pause();
+                }
+                let link = ctx.link().clone();
+                Timeout::new(RECONNECT_DELAY_MS, move || link.send_message(Msg::Reconnect))
+                    .forget();
+                true
+            }
+            Msg::Reconnect if self.leaving => false,
+            Msg::Reconnect => {
+                self.wss = WebsocketService::new();
+                Self::register(&self.wss);
+
+                let paused: Vec<(u64, u32)> = self
+                    .outgoing
+                    .iter_mut()
+                    .filter(|(_, t)| t.state == TransferState::Paused)
+                    .map(|(id, t)| {
+                        t.state = TransferState::InProgress;
+                        (*id, t.first_unacknowledged())
+                    })
+                    .collect();
+                for (id, from_index) in paused {
+                    self.stream_chunks(id, from_index);
+                }
+
+                // Ask senders to fill in whatever was lost while we were away.
+                for transfer in self.incoming.values().filter(|t| t.is_pending()) {
+                    self.send_file_control(&FileControl::Resume {
+                        id: transfer.offer.id,
+                        from_index: transfer.first_missing(),
+                    });
+                }
+                true
             }
             Msg::SubmitMessage => {
                 let input = self.chat_input.cast::<HtmlInputElement>();
                 if let Some(input) = input {
                     let message_text = input.value();
-                    if !message_text.is_empty() {
-                        let message = WebSocketMessage {
-                            message_type: MsgTypes::Message,
-                            data: Some(message_text),
-                            data_array: None,
+                    if !message_text.trim().is_empty() {
+                        let message_text =
+                            emoji::replace_shortcodes(&message_text, self.settings.skin_tone);
+                        let result = match commands::with_registry(|r| r.parse(&message_text)) {
+                            Parsed::Text(text) => {
+                                self.send(MsgTypes::Message, text);
+                                Ok(())
+                            }
+                            Parsed::Action(action) => self.run_command(action),
+                            Parsed::Error(e) => Err(e),
                         };
-                        if let Err(e) = self
-                            .wss
-                            .tx
-                            .clone()
-                            .try_send(serde_json::to_string(&message).unwrap())
-                        {
-                            log::debug!("error sending to channel: {:?}", e);
+                        if let Err(e) = result {
+                            // Keep the text so the command can be fixed.
+                            self.composer_error = Some(e);
+                            return true;
                         }
                         input.set_value("");
+                        self.composer_text.clear();
                     }
                 };
-                false
+                self.composer_error = None;
+                let attachments = std::mem::take(&mut self.attachments);
+                for image in attachments {
+                    self.send(MsgTypes::Message, image.data_url);
+                }
+                true
             }
             Msg::ToggleEmojiPicker => {
                 self.show_emoji_picker = !self.show_emoji_picker;
@@ -149,17 +683,465 @@ impl Component for Chat {
                 if let Some(input) = self.chat_input.cast::<HtmlInputElement>() {
                     let current = input.value();
                     input.set_value(&format!("{} {}", current, emoji));
+                    self.composer_text = input.value();
                 }
+                emoji::push_recent(&mut self.recent_emoji, &emoji);
                 self.show_emoji_picker = false;
                 true
             }
+            Msg::ToggleSettings => {
+                self.show_settings = !self.show_settings;
+                true
+            }
+            Msg::UpdateSettings(settings) => {
+                store::dispatch(Action::UpdateSettings(settings));
+                false
+            }
+            Msg::UsernameChanged(username) => {
+                self.username = username;
+                true
+            }
+            Msg::SettingsChanged(settings) => {
+                let rewatch = settings.auto_away_minutes != self.settings.auto_away_minutes;
+                self.settings = settings;
+                if rewatch {
+                    self.watch_idle(ctx);
+                }
+                self.update_dnd();
+                true
+            }
+            Msg::ConnectionChanged(connection) => {
+                self.connection = connection;
+                true
+            }
+            Msg::ToggleStatusMenu => {
+                self.show_status_menu = !self.show_status_menu;
+                true
+            }
+            Msg::SetPresence(presence) => {
+                self.set_presence(presence);
+                self.show_status_menu = false;
+                true
+            }
+            Msg::FlushRoster => {
+                let events = self.roster.flush(js_sys::Date::now());
+                self.push_roster_events(events);
+                self.schedule_roster_flush(ctx);
+                true
+            }
+            Msg::Tick => {
+                self.now = js_sys::Date::now();
+                self.update_dnd();
+                true
+            }
+            Msg::StartDirect(name) => {
+                self.profile = None;
+                self.show_drawer = false;
+                if let Some(input) = self.chat_input.cast::<HtmlInputElement>() {
+                    let text = format!("/dm {} ", name);
+                    input.set_value(&text);
+                    let _ = input.focus();
+                    self.composer_text = text;
+                }
+                true
+            }
+            Msg::ShowProfile(name) => {
+                if name.is_some() {
+                    self.show_drawer = false;
+                }
+                self.profile = name;
+                self.now = js_sys::Date::now();
+                true
+            }
+            Msg::ToggleDrawer => {
+                self.show_drawer = !self.show_drawer;
+                true
+            }
+            Msg::TouchStart(point) => {
+                self.swipe.start(point);
+                false
+            }
+            Msg::TouchEnd(point) => match self.swipe.end(point) {
+                Some(Swipe::Right { from_edge: true }) if !self.show_drawer => {
+                    self.show_drawer = true;
+                    true
+                }
+                Some(Swipe::Left) if self.show_drawer => {
+                    self.show_drawer = false;
+                    true
+                }
+                _ => false,
+            },
+            Msg::KeyboardResized(height) => {
+                let changed = self.keyboard_height != height;
+                self.keyboard_height = height;
+                changed
+            }
+            Msg::RequestNotifications => {
+                ctx.link().send_future(async {
+                    Msg::NotificationPermissionChanged(notifications::request_permission().await)
+                });
+                false
+            }
+            Msg::NotificationPermissionChanged(permission) => {
+                self.notification_permission = permission;
+                true
+            }
+            Msg::DismissNotificationPrompt => {
+                let mut settings = self.settings.clone();
+                settings.notifications.prompt_dismissed = true;
+                store::dispatch(Action::UpdateSettings(settings));
+                false
+            }
+            Msg::JumpToMessage(id) => {
+                let element = web_sys::window()
+                    .and_then(|w| w.document())
+                    .and_then(|d| d.get_element_by_id(&message_id(id)));
+                let element = match element {
+                    Some(element) => element,
+                    // Cleared from the timeline since.
+                    None => return false,
+                };
+                element.scroll_into_view();
+                self.highlighted = Some(id);
+                let link = ctx.link().clone();
+                self.highlight_timer =
+                    Some(Timeout::new(HIGHLIGHT_MS, move || link.send_message(Msg::ClearHighlight)));
+                true
+            }
+            Msg::ClearHighlight => {
+                self.highlighted = None;
+                self.highlight_timer = None;
+                true
+            }
+            Msg::PreviewSound((event, volume)) => {
+                self.sounds.preview(event, volume);
+                false
+            }
+            Msg::SetDnd(on) => {
+                let mut settings = self.settings.clone();
+                settings.dnd.manual = on;
+                store::dispatch(Action::UpdateSettings(settings));
+                self.show_status_menu = false;
+                true
+            }
+            Msg::WindowFocused => {
+                // They've been seen now.
+                self.notifications.clear();
+                false
+            }
+            Msg::Mention(name) => {
+                self.profile = None;
+                if let Some(input) = self.chat_input.cast::<HtmlInputElement>() {
+                    let current = input.value();
+                    let text = if current.is_empty() || current.ends_with(' ') {
+                        format!("{}@{} ", current, name)
+                    } else {
+                        format!("{} @{} ", current, name)
+                    };
+                    input.set_value(&text);
+                    let _ = input.focus();
+                    self.composer_text = text;
+                }
+                true
+            }
+            Msg::ToggleIgnore(name) => {
+                let mut settings = self.settings.clone();
+                settings.toggle_ignored(&name);
+                store::dispatch(Action::UpdateSettings(settings));
+                false
+            }
+            Msg::IdleChanged(idle) => {
+                let before = self.effective_presence();
+                self.idle = idle;
+                if self.effective_presence() != before {
+                    self.send_presence();
+                }
+                true
+            }
+            Msg::Rename(name) => {
+                if let Err(e) = self.request_rename(&name) {
+                    self.rename_error = Some(e);
+                }
+                true
+            }
+            Msg::RoomChanged(room) => {
+                self.active_room = room;
+                true
+            }
+            Msg::SelectSidebarTab(tab) => {
+                self.sidebar_tab = tab;
+                true
+            }
+            Msg::OpenLightbox(index) => {
+                self.lightbox = Some(index);
+                true
+            }
+            Msg::CloseLightbox => {
+                self.lightbox = None;
+                true
+            }
+            Msg::PickFile => {
+                if let Some(input) = self.file_input.cast::<HtmlInputElement>() {
+                    input.click();
+                }
+                false
+            }
+            Msg::FilesSelected => {
+                let input = match self.file_input.cast::<HtmlInputElement>() {
+                    Some(input) => input,
+                    None => return false,
+                };
+                self.composer_error = None;
+                self.send_files(ctx, files_from(input.files()));
+                input.set_value("");
+                true
+            }
+            Msg::FileLoaded(result) => match result {
+                Ok(transfer) => {
+                    let offer = serde_json::to_string(&transfer.offer).unwrap();
+                    self.outgoing.insert(transfer.offer.id, transfer);
+                    self.send(MsgTypes::File, offer);
+                    false
+                }
+                Err(e) => {
+                    self.composer_error = Some(e);
+                    true
+                }
+            },
+            Msg::AttachFiles(files) => {
+                self.composer_error = None;
+                self.dragging = false;
+                let (images, others): (Vec<File>, Vec<File>) =
+                    files.into_iter().partition(image::is_image);
+                for file in images {
+                    self.preparing_images += 1;
+                    let prepared = ctx.link().callback(Msg::ImagePrepared);
+                    spawn_local(async move { prepared.emit(image::prepare_image(file).await) });
+                }
+                self.send_files(ctx, others);
+                true
+            }
+            Msg::ImagePrepared(result) => {
+                self.preparing_images = self.preparing_images.saturating_sub(1);
+                match result {
+                    Ok(image) => self.attachments.push(image),
+                    Err(e) => self.composer_error = Some(e),
+                }
+                true
+            }
+            Msg::RemoveAttachment(index) => {
+                if index < self.attachments.len() {
+                    self.attachments.remove(index);
+                }
+                true
+            }
+            Msg::SetDragging(dragging) => {
+                let changed = self.dragging != dragging;
+                self.dragging = dragging;
+                changed
+            }
+            Msg::ComposerInput(text) => {
+                let was_completing = autocomplete_active(&self.composer_text);
+                self.composer_text = text;
+                if self.composer_error.is_some() {
+                    self.composer_error = None;
+                    return true;
+                }
+                was_completing || autocomplete_active(&self.composer_text)
+            }
+            Msg::CompleteShortcode(choice) => {
+                let choice = choice.or_else(|| {
+                    emoji::shortcode_query(&self.composer_text)
+                        .and_then(|q| emoji::shortcode_suggestions(q).into_iter().next())
+                        .map(|(_, e)| emoji::with_tone(e, self.settings.skin_tone).to_string())
+                });
+                if let (Some(choice), Some(input)) = (choice, self.chat_input.cast::<HtmlInputElement>()) {
+                    let completed = emoji::complete_shortcode(&self.composer_text, &choice);
+                    input.set_value(&completed);
+                    let _ = input.focus();
+                    self.composer_text = completed;
+                    emoji::push_recent(&mut self.recent_emoji, &choice);
+                    return true;
+                }
+                false
+            }
+            Msg::SetSkinTone(tone) => {
+                let mut settings = self.settings.clone();
+                settings.skin_tone = tone;
+                store::dispatch(Action::UpdateSettings(settings));
+                false
+            }
+            Msg::SendSticker(data_url) => {
+                self.send(MsgTypes::Message, data_url);
+                self.show_emoji_picker = false;
+                true
+            }
+            Msg::ImportPack(file) => {
+                let loaded = ctx.link().callback(Msg::PackLoaded);
+                spawn_local(async move {
+                    let result = gloo::file::futures::read_as_text(&file)
+                        .await
+                        .map_err(|e| format!("Could not read {}: {}", file.name(), e))
+                        .and_then(|json| custom_emoji::parse_manifest(&json));
+                    loaded.emit(result);
+                });
+                false
+            }
+            Msg::PackLoaded(result) => {
+                match result {
+                    Ok(pack) => {
+                        let name = pack.name.clone();
+                        match custom_emoji::install(&mut self.emoji_packs, pack, false) {
+                            Ok(()) => self.push_message(MessageData::system(format!(
+                                "Installed the emoji pack \"{}\"",
+                                name
+                            ))),
+                            Err(e) => self.composer_error = Some(format!("{}; remove it first", e)),
+                        }
+                    }
+                    Err(e) => self.composer_error = Some(e),
+                }
+                true
+            }
+            Msg::AcceptPack(index) => {
+                if index < self.pack_offers.len() {
+                    let shared = self.pack_offers.remove(index);
+                    let name = shared.pack.name.clone();
+                    // The offer said whether it replaces a pack, so accepting is consent.
+                    if let Err(e) = custom_emoji::install(&mut self.emoji_packs, shared.pack, true) {
+                        self.composer_error = Some(e);
+                    } else {
+                        self.push_message(MessageData::system(format!(
+                            "Installed the emoji pack \"{}\" from {}",
+                            name, shared.from
+                        )));
+                    }
+                }
+                true
+            }
+            Msg::DeclinePack(index) => {
+                if index < self.pack_offers.len() {
+                    self.pack_offers.remove(index);
+                }
+                true
+            }
+            Msg::SharePack(name) => {
+                if let Some(pack) = self.emoji_packs.iter().find(|p| p.name == name) {
+                    self.send(MsgTypes::EmojiPack, serde_json::to_string(pack).unwrap());
+                }
+                false
+            }
+            Msg::RemovePack(name) => {
+                custom_emoji::remove(&mut self.emoji_packs, &name);
+                true
+            }
+            Msg::TokenRefreshed(_) if self.leaving => false,
+            Msg::TokenRefreshed(Ok(token)) => {
+                session::save_auth(&token);
+                self.schedule_token_refresh(ctx);
+                false
+            }
+            Msg::TokenRefreshed(Err(e)) => {
+                self.push_message(MessageData::system(format!(
+                    "Couldn't renew your sign-in: {}. You'll have to sign in again if the connection drops.",
+                    e
+                )));
+                true
+            }
+            Msg::Logout => {
+                if let Some(warning) = self.outbox_warning() {
+                    let confirmed = web_sys::window()
+                        .and_then(|w| w.confirm_with_message(&warning).ok())
+                        .unwrap_or(true);
+                    if !confirmed {
+                        return false;
+                    }
+                }
+
+                self.leaving = true;
+                self.refresh_timer = None;
+                let unfinished: Vec<u64> = self
+                    .outgoing
+                    .iter()
+                    .filter(|(_, t)| matches!(t.state, TransferState::InProgress | TransferState::Paused))
+                    .map(|(id, _)| *id)
+                    .collect();
+                for id in unfinished {
+                    if let Some(transfer) = self.outgoing.get_mut(&id) {
+                        transfer.cancel();
+                    }
+                    self.send_file_control(&FileControl::Cancel { id });
+                }
+                // Queued ahead of the close, so the server drops us from the
+                // user list straight away instead of at its next sweep.
+                self.send(MsgTypes::Leave, String::new());
+                self.wss.close();
+
+                store::dispatch(Action::SignOut);
+                session::clear();
+                session::forget();
+
+                ctx.link().history().unwrap().push(Route::Login);
+                false
+            }
+            Msg::CompleteCommand(name) => {
+                let name = name.or_else(|| {
+                    commands::with_registry(|r| r.suggestions(&self.composer_text))
+                        .first()
+                        .map(|c| c.name().to_string())
+                });
+                if let (Some(name), Some(input)) = (name, self.chat_input.cast::<HtmlInputElement>()) {
+                    let completed = format!("/{} ", name);
+                    input.set_value(&completed);
+                    let _ = input.focus();
+                    self.composer_text = completed;
+                    return true;
+                }
+                false
+            }
+            Msg::CancelTransfer(id) => {
+                if let Some(transfer) = self.outgoing.get_mut(&id) {
+                    transfer.cancel();
+                    self.send_file_control(&FileControl::Cancel { id });
+                } else if let Some(transfer) = self.incoming.get_mut(&id) {
+                    transfer.cancel();
+                }
+                true
+            }
         }
     }
 
     fn view(&self, ctx: &Context<Self>) -> Html {
+        let media = self.media_items();
+        let open_lightbox = ctx.link().callback(Msg::OpenLightbox);
         let submit = ctx.link().callback(|_| Msg::SubmitMessage);
         let toggle_emoji = ctx.link().callback(|_| Msg::ToggleEmojiPicker);
-        // let logout = ctx.link().callback(|_| Msg::Logout); // Unused
+        let toggle_settings = ctx.link().callback(|_| Msg::ToggleSettings);
+        let onpaste = ctx.link().batch_callback(|e: Event| {
+            let files = files_from(
+                e.dyn_ref::<ClipboardEvent>()
+                    .and_then(|e| e.clipboard_data())
+                    .and_then(|data| data.files()),
+            );
+            let images: Vec<File> = files.into_iter().filter(image::is_image).collect();
+            if images.is_empty() {
+                None
+            } else {
+                e.prevent_default();
+                Some(Msg::AttachFiles(images))
+            }
+        });
+        let ondragover = ctx.link().callback(|e: DragEvent| {
+            e.prevent_default();
+            Msg::SetDragging(true)
+        });
+        let ondragleave = ctx.link().callback(|_: DragEvent| Msg::SetDragging(false));
+        let ondrop = ctx.link().callback(|e: DragEvent| {
+            e.prevent_default();
+            Msg::AttachFiles(files_from(e.data_transfer().and_then(|data| data.files())))
+        });
+        let handle_logout = ctx.link().callback(|_| Msg::Logout);
         let onkeypress = ctx.link().batch_callback(|e: KeyboardEvent| {
             if e.key() == "Enter" {
                 e.prevent_default();
@@ -168,47 +1150,119 @@ impl Component for Chat {
                 None
             }
         });
+        let onkeydown = ctx.link().batch_callback(|e: KeyboardEvent| {
+            if e.key() != "Tab" {
+                return None;
+            }
+            let input: HtmlInputElement = e.target_unchecked_into();
+            let value = input.value();
+            if value.starts_with('/') && !value.contains(char::is_whitespace) {
+                e.prevent_default();
+                Some(Msg::CompleteCommand(None))
+            } else if emoji::shortcode_query(&value).is_some() {
+                e.prevent_default();
+                Some(Msg::CompleteShortcode(None))
+            } else {
+                None
+            }
+        });
+        let oninput = ctx.link().callback(|e: InputEvent| {
+            let input: HtmlInputElement = e.target_unchecked_into();
+            Msg::ComposerInput(input.value())
+        });
+        let suggestions = commands::with_registry(|r| r.suggestions(&self.composer_text));
+        let shortcodes = emoji::shortcode_query(&self.composer_text)
+            .map(emoji::shortcode_suggestions)
+            .unwrap_or_default();
         
-        let handle_logout = {
-            let history = ctx.link().history().unwrap();
-            Callback::from(move |_| {
-                history.push(Route::Login);
-            })
-        };
-
-        let (user, _) = ctx
-            .link()
-            .context::<User>(Callback::noop())
-            .expect("context to be set");
-        let username = user.username.borrow().clone();
+        let username = self.username.clone();
+        let own_presence = self.effective_presence();
         
-        // Find the current user in the list
-        let current_user_avatar = self.users.iter()
-            .find(|u| u.name == username)
-            .map(|u| u.avatar.clone())
-            .unwrap_or_else(|| format!(
-                "https://avatars.dicebear.com/api/adventurer-neutral/{}.svg",
-                username.clone()
-            ));
-            
+        let avatar_style = self.settings.avatar_style;
+        let current_user_avatar = avatar_url(&username, avatar_style);
+
         let username_clone = username.clone();
+        let ontouchstart = ctx.link().batch_callback(|e: TouchEvent| {
+            gestures::touch_point(&e, false).map(Msg::TouchStart)
+        });
+        let ontouchend = ctx.link().batch_callback(|e: TouchEvent| {
+            gestures::touch_point(&e, true).map(Msg::TouchEnd)
+        });
+        // Keeps the composer above the on-screen keyboard.
+        let root_style = viewport::keyboard_style(self.keyboard_height);
 
         html! {
-            <div class="flex flex-col h-screen w-full bg-gray-100">
+            <div class="flex flex-col h-screen w-full bg-gray-100" style={root_style} {ontouchstart} {ontouchend}>
                 // Header
-                <header class="bg-gradient-to-r from-purple-800 to-indigo-800 text-white shadow-lg">
+                <header class="relative bg-gradient-to-r from-purple-800 to-indigo-800 text-white shadow-lg">
                     <div class="container mx-auto px-4 py-3 flex justify-between items-center">
                         <div class="flex items-center space-x-2">
-                            <svg xmlns="http://www.w3.org/2000/svg" class="h-8 w-8" viewBox="0 0 20 20" fill="currentColor">
+                            <DrawerToggle
+                                open={self.show_drawer}
+                                count={self.users.len()}
+                                ontoggle={ctx.link().callback(|_| Msg::ToggleDrawer)}
+                            />
+                            <svg xmlns="http://www.w3.org/2000/svg" class="h-8 w-8 hidden sm:block" viewBox="0 0 20 20" fill="currentColor">
                                 <path fill-rule="evenodd" d="M18 10c0 3.866-3.582 7-8 7a8.841 8.841 0 01-4.083-.98L2 17l1.338-3.123C2.493 12.767 2 11.434 2 10c0-3.866 3.582-7 8-7s8 3.134 8 7zM7 9H5v2h2V9zm8 0h-2v2h2V9zM9 9h2v2H9V9z" clip-rule="evenodd" />
                             </svg>
                             <h1 class="text-2xl font-bold">{"YewChat"}</h1>
+                            if let Some(room) = &self.active_room {
+                                <span class="text-purple-200 font-medium">{format!("#{}", room)}</span>
+                            }
+                            {
+                                match self.connection {
+                                    Connection::Connected => html! {},
+                                    Connection::Reconnecting => html! {
+                                        <span class="ml-2 rounded bg-amber-400/90 px-2 py-0.5 text-xs text-amber-900">{"Reconnecting…"}</span>
+                                    },
+                                    Connection::Connecting | Connection::Disconnected => html! {
+                                        <span class="ml-2 rounded bg-white/20 px-2 py-0.5 text-xs">{"Connecting…"}</span>
+                                    },
+                                }
+                            }
                         </div>
                         <div class="flex items-center space-x-4">
-                            <div class="flex items-center space-x-2">
-                                <img class="w-8 h-8 rounded-full ring-2 ring-white" src={current_user_avatar} alt="Your avatar"/>
-                                <span class="font-medium hidden md:inline">{username}</span>
+                            <div class="relative flex items-center space-x-2">
+                                <button
+                                    onclick={ctx.link().callback(|_| Msg::ToggleStatusMenu)}
+                                    class="relative flex items-center space-x-2 rounded-lg hover:bg-white/10 px-1"
+                                    title="Set your status"
+                                >
+                                    <img class="w-8 h-8 rounded-full ring-2 ring-white" src={current_user_avatar} alt="Your avatar"/>
+                                    <span class={classes!(
+                                        "absolute", "bottom-0", "left-7", "w-3", "h-3", "rounded-full", "border-2", "border-purple-800",
+                                        own_presence.status.color()
+                                    )}></span>
+                                    <span class="font-medium hidden md:inline">{username}</span>
+                                </button>
+                                if self.show_status_menu {
+                                    <StatusMenu
+                                        presence={self.presence.clone()}
+                                        onchange={ctx.link().callback(Msg::SetPresence)}
+                                        dnd={self.settings.dnd.manual}
+                                        ondnd={ctx.link().callback(Msg::SetDnd)}
+                                        onclose={ctx.link().callback(|_| Msg::ToggleStatusMenu)}
+                                    />
+                                }
+                                if self.remembered {
+                                    <button
+                                        onclick={handle_logout.clone()}
+                                        class="text-xs text-purple-200 hover:text-white underline"
+                                        title="Sign out and forget this device"
+                                    >
+                                        {"Not you?"}
+                                    </button>
+                                }
                             </div>
+                            <button
+                                onclick={toggle_settings}
+                                class="bg-white/20 hover:bg-white/30 transition-colors rounded-lg p-1"
+                                title="Settings"
+                            >
+                                <svg xmlns="http://www.w3.org/2000/svg" class="h-5 w-5" viewBox="0 0 20 20" fill="currentColor">
+                                    <path fill-rule="evenodd" d="M11.49 3.17c-.38-1.56-2.6-1.56-2.98 0a1.532 1.532 0 01-2.286.948c-1.372-.836-2.942.734-2.106 2.106.54.886.061 2.042-.947 2.287-1.561.379-1.561 2.6 0 2.978a1.532 1.532 0 01.947 2.287c-.836 1.372.734 2.942 2.106 2.106a1.532 1.532 0 012.287.947c.379 1.561 2.6 1.561 2.978 0a1.533 1.533 0 012.287-.947c1.372.836 2.942-.734 2.106-2.106a1.533 1.533 0 01.947-2.287c1.561-.379 1.561-2.6 0-2.978a1.532 1.532 0 01-.947-2.287c.836-1.372-.734-2.942-2.106-2.106a1.532 1.532 0 01-2.287-.947zM10 13a3 3 0 100-6 3 3 0 000 6z" clip-rule="evenodd" />
+                                </svg>
+                            </button>
                             <button 
                                 onclick={handle_logout}
                                 class="bg-white/20 hover:bg-white/30 transition-colors rounded-lg px-3 py-1 text-sm flex items-center"
@@ -220,71 +1274,131 @@ impl Component for Chat {
                             </button>
                         </div>
                     </div>
+                    {
+                        if self.show_settings {
+                            html! {
+                                <SettingsPanel
+                                    settings={self.settings.clone()}
+                                    username={username_clone.clone()}
+                                    onchange={ctx.link().callback(Msg::UpdateSettings)}
+                                    onclose={ctx.link().callback(|_| Msg::ToggleSettings)}
+                                    packs={self.emoji_packs.clone()}
+                                    onimportpack={ctx.link().callback(Msg::ImportPack)}
+                                    onsharepack={ctx.link().callback(Msg::SharePack)}
+                                    onremovepack={ctx.link().callback(Msg::RemovePack)}
+                                    onrename={ctx.link().callback(Msg::Rename)}
+                                    rename_error={self.rename_error.clone()}
+                                    room={self.active_room.clone()}
+                                    notification_permission={self.notification_permission}
+                                    onrequestnotifications={ctx.link().callback(|_| Msg::RequestNotifications)}
+                                    onpreviewsound={ctx.link().callback(Msg::PreviewSound)}
+                                />
+                            }
+                        } else {
+                            html! {}
+                        }
+                    }
                 </header>
+                if self.notification_permission == NotificationPermission::Default
+                    && notifications::supported()
+                    && !self.settings.notifications.prompt_dismissed
+                {
+                    <div class="flex items-center justify-center gap-3 bg-purple-50 border-b border-purple-100 px-4 py-2 text-sm text-purple-900">
+                        <span>{"Get a desktop notification when someone mentions you or sends you a direct message?"}</span>
+                        <button
+                            onclick={ctx.link().callback(|_| Msg::RequestNotifications)}
+                            class="px-3 py-1 text-xs rounded bg-purple-600 text-white hover:bg-purple-700"
+                        >
+                            {"Turn on"}
+                        </button>
+                        <button
+                            onclick={ctx.link().callback(|_| Msg::DismissNotificationPrompt)}
+                            class="text-xs text-purple-700 hover:underline"
+                        >
+                            {"Not now"}
+                        </button>
+                    </div>
+                }
+
+                {
+                    for self.pack_offers.iter().enumerate().map(|(index, offer)| {
+                        let replaces = custom_emoji::is_installed(&self.emoji_packs, &offer.pack.name);
+                        html! {
+                            <div class="flex items-center justify-center gap-3 bg-purple-50 border-b border-purple-100 px-4 py-2 text-sm text-purple-900">
+                                <span>
+                                    {format!(
+                                        "{} shared the emoji pack \"{}\" ({} emoji, {} stickers)",
+                                        offer.from, offer.pack.name, offer.pack.emoji.len(), offer.pack.stickers.len()
+                                    )}
+                                </span>
+                                <button
+                                    onclick={ctx.link().callback(move |_| Msg::AcceptPack(index))}
+                                    class="px-3 py-1 text-xs rounded bg-purple-600 text-white hover:bg-purple-700"
+                                >
+                                    { if replaces { "Replace mine" } else { "Install" } }
+                                </button>
+                                <button
+                                    onclick={ctx.link().callback(move |_| Msg::DeclinePack(index))}
+                                    class="text-xs text-purple-700 hover:underline"
+                                >
+                                    {"Dismiss"}
+                                </button>
+                            </div>
+                        }
+                    })
+                }
 
                 // Main Content
                 <div class="flex flex-1 overflow-hidden w-full">
-                    // Sidebar
-                    <div class="hidden md:block w-64 bg-white shadow-md">
-                        <div class="p-4 border-b">
-                            <h2 class="text-lg font-semibold text-gray-700 flex items-center">
+                    // Sidebar: a slide-in drawer on small screens
+                    <Drawer open={self.show_drawer} onclose={ctx.link().callback(|_| Msg::ToggleDrawer)}>
+                        <div class="flex border-b">
+                            <button
+                                onclick={ctx.link().callback(|_| Msg::SelectSidebarTab(SidebarTab::Users))}
+                                class={classes!(
+                                    "flex-1", "p-4", "text-sm", "font-semibold", "flex", "items-center", "justify-center",
+                                    if self.sidebar_tab == SidebarTab::Users { "text-purple-700 border-b-2 border-purple-600" } else { "text-gray-500 hover:text-gray-700" }
+                                )}
+                            >
                                 <svg xmlns="http://www.w3.org/2000/svg" class="h-5 w-5 mr-2 text-purple-600" viewBox="0 0 20 20" fill="currentColor">
                                     <path d="M13 6a3 3 0 11-6 0 3 3 0 016 0zM18 8a2 2 0 11-4 0 2 2 0 014 0zM14 15a4 4 0 00-8 0v3h8v-3zM6 8a2 2 0 11-4 0 2 2 0 014 0zM16 18v-3a5.972 5.972 0 00-.75-2.906A3.005 3.005 0 0119 15v3h-3zM4.75 12.094A5.973 5.973 0 004 15v3H1v-3a3 3 0 013.75-2.906z" />
                                 </svg>
                                 {"Online Users"}
                                 <span class="ml-2 bg-purple-600 text-white text-xs rounded-full px-2 py-1">{self.users.len()}</span>
-                            </h2>
+                            </button>
+                            <button
+                                onclick={ctx.link().callback(|_| Msg::SelectSidebarTab(SidebarTab::Media))}
+                                class={classes!(
+                                    "flex-1", "p-4", "text-sm", "font-semibold", "flex", "items-center", "justify-center",
+                                    if self.sidebar_tab == SidebarTab::Media { "text-purple-700 border-b-2 border-purple-600" } else { "text-gray-500 hover:text-gray-700" }
+                                )}
+                            >
+                                {"Media"}
+                                <span class="ml-2 bg-gray-200 text-gray-700 text-xs rounded-full px-2 py-1">{media.len()}</span>
+                            </button>
                         </div>
                         <div class="overflow-y-auto h-full">
-                            {
-                                if self.users.is_empty() {
-                                    html! {
-                                        <div class="p-4 text-center text-gray-500 italic">
-                                            {"No users online"}
-                                        </div>
-                                    }
-                                } else {
-                                    html! {
-                                        <ul class="divide-y">
-                                            {
-                                                self.users.clone().iter().map(|u| {
-                                                    let is_current = u.name == username_clone;
-                                                    html!{
-                                                        <li class={classes!("p-3", "hover:bg-gray-50", if is_current {"bg-purple-50"} else {""})}>
-                                                            <div class="flex items-center space-x-3">
-                                                                <div class="relative">
-                                                                    <img class="w-10 h-10 rounded-full" src={u.avatar.clone()} alt="avatar"/>
-                                                                    <div class="absolute bottom-0 right-0 w-3 h-3 bg-green-500 rounded-full border-2 border-white"></div>
-                                                                </div>
-                                                                <div>
-                                                                    <div class="text-sm font-medium text-gray-900 flex items-center">
-                                                                        {u.name.clone()}
-                                                                        {
-                                                                            if is_current {
-                                                                                html! {
-                                                                                    <span class="ml-2 text-xs text-purple-600">{" (you)"}</span>
-                                                                                }
-                                                                            } else {
-                                                                                html! {}
-                                                                            }
-                                                                        }
-                                                                    </div>
-                                                                    <div class="text-xs text-gray-500">{"Online"}</div>
-                                                                </div>
-                                                            </div>
-                                                        </li>
-                                                    }
-                                                }).collect::<Html>()
-                                            }
-                                        </ul>
-                                    }
-                                }
+                            if self.sidebar_tab == SidebarTab::Media {
+                                <MediaGallery items={media.clone()} onselect={open_lightbox.clone()}/>
+                            } else {
+                                <UserList
+                                    users={self.user_list_entries()}
+                                    current={username_clone.clone()}
+                                    {avatar_style}
+                                    onselect={ctx.link().callback(|name| Msg::ShowProfile(Some(name)))}
+                                />
+                                {self.view_offline_users(ctx)}
                             }
                         </div>
-                    </div>
+                    </Drawer>
                     
                     // Chat Area
-                    <div class="flex-1 flex flex-col bg-gray-50 w-full">
+                    <div class="relative flex-1 flex flex-col bg-gray-50 w-full" {ondragover} {ondragleave} {ondrop}>
+                        if self.dragging {
+                            <div class="absolute inset-0 z-10 m-2 flex items-center justify-center rounded-xl border-4 border-dashed border-purple-400 bg-purple-50/90 text-purple-700 text-lg font-semibold pointer-events-none">
+                                {"Drop to attach"}
+                            </div>
+                        }
                         // Messages
                         <div class="flex-1 overflow-y-auto p-4 space-y-4">
                             {
@@ -303,11 +1417,33 @@ impl Component for Chat {
                                         <>
                                             {
                                                 self.messages.iter().map(|m| {
+                                                    if m.kind == EntryKind::Roster && !self.settings.show_join_leave {
+                                                        return html! {};
+                                                    }
+                                                    if matches!(m.kind, EntryKind::Chat | EntryKind::File(_))
+                                                        && self.settings.is_ignored(&m.from)
+                                                    {
+                                                        return html! {};
+                                                    }
+                                                    if matches!(m.kind, EntryKind::System | EntryKind::Roster) {
+                                                        return html! {
+                                                            <div class="text-center text-xs text-gray-500 whitespace-pre-wrap">{m.message.clone()}</div>
+                                                        };
+                                                    }
+                                                    if m.emote {
+                                                        return html! {
+                                                            <div id={message_id(m.id)} class={classes!("text-sm", "italic", "text-purple-700", "px-2", self.highlight_class(m.id))}>
+                                                                {format!("* {} {}", m.from, m.message)}
+                                                            </div>
+                                                        };
+                                                    }
+
                                                     let user_option = self.users.iter().find(|u| u.name == m.from);
                                                     
                                                     let default_profile = UserProfile {
                                                         name: m.from.clone(),
-                                                        avatar: format!("https://avatars.dicebear.com/api/adventurer-neutral/{}.svg", m.from),
+                                                        presence: Presence::default(),
+                                                        account: false,
                                                     };
                                                     
                                                     let user = match user_option {
@@ -317,18 +1453,20 @@ impl Component for Chat {
                                                     
                                                     let is_current_user = user.name == username_clone;
                                                     let current_time = "now";
+                                                    let show_profile = self.profile_callback(ctx, &user.name);
                                                     
                                                     html!{
-                                                        <div class={classes!(
+                                                        <div id={message_id(m.id)} class={classes!(
                                                             "flex", 
                                                             "items-end", 
                                                             "gap-2",
-                                                            if is_current_user { "justify-end" } else { "justify-start" }
+                                                            if is_current_user { "justify-end" } else { "justify-start" },
+                                                            self.highlight_class(m.id)
                                                         )}>
                                                             {
                                                                 if !is_current_user {
                                                                     html! {
-                                                                        <img class="w-8 h-8 rounded-full" src={user.avatar.clone()} alt="avatar"/>
+                                                                        <img onclick={show_profile.clone()} class="w-8 h-8 rounded-full cursor-pointer" src={avatar_url(&user.name, avatar_style)} alt="avatar"/>
                                                                     }
                                                                 } else {
                                                                     html! {}
@@ -349,7 +1487,19 @@ impl Component for Chat {
                                                                         "font-semibold", "text-sm",
                                                                         if is_current_user { "text-purple-100" } else { "text-purple-600" }
                                                                     )}>
-                                                                        {user.name.clone()}
+                                                                        <span onclick={show_profile.clone()} class="cursor-pointer hover:underline">{user.name.clone()}</span>
+                                                                        if let Some(old) = &m.sent_as {
+                                                                            <span class="ml-1 font-normal opacity-75">{format!("(as {})", old)}</span>
+                                                                        }
+                                                                        {self.view_lookalike_marker(&user.name)}
+                                                                        {
+                                                                            match &m.to {
+                                                                                Some(to) => html! {
+                                                                                    <span class="ml-1 font-normal opacity-75">{format!("→ {} (private)", to)}</span>
+                                                                                },
+                                                                                None => html! {},
+                                                                            }
+                                                                        }
                                                                     </span>
                                                                     <span class={classes!(
                                                                         "text-xs", "ml-2",
@@ -359,17 +1509,40 @@ impl Component for Chat {
                                                                     </span>
                                                                 </div>
                                                                 <div>
-                                                                    if m.message.ends_with(".gif") {
-                                                                        <img class="rounded-lg max-w-full" src={m.message.clone()}/>
-                                                                    } else {
-                                                                        <p class="whitespace-pre-wrap break-words">{m.message.clone()}</p>
+                                                                    {
+                                                                        if let EntryKind::File(id) = m.kind {
+                                                                            self.view_file_card(ctx, id, is_current_user)
+                                                                        } else {
+                                                                            match media.iter().position(|item| item.message_id == m.id) {
+                                                                                Some(index) => {
+                                                                                    let kind = media[index].kind;
+                                                                                    let onclick = open_lightbox.reform(move |_: MouseEvent| index);
+                                                                                    match kind {
+                                                                                        MediaKind::Image => html! {
+                                                                                            <img {onclick} class="rounded-lg max-w-full cursor-pointer" src={m.message.clone()}/>
+                                                                                        },
+                                                                                        MediaKind::Video => html! {
+                                                                                            <div class="relative">
+                                                                                                <video class="rounded-lg max-w-full" src={m.message.clone()} controls=true preload="metadata"/>
+                                                                                                <button {onclick} class="absolute top-1 right-1 bg-black/50 text-white text-xs rounded px-2 py-1">{"⤢"}</button>
+                                                                                            </div>
+                                                                                        },
+                                                                                    }
+                                                                                }
+                                                                                None => html! {
+                                                                                    <p class="whitespace-pre-wrap break-words">
+                                                                                        { self.view_text(&m.message) }
+                                                                                    </p>
+                                                                                },
+                                                                            }
+                                                                        }
                                                                     }
                                                                 </div>
                                                             </div>
                                                             {
                                                                 if is_current_user {
                                                                     html! {
-                                                                        <img class="w-8 h-8 rounded-full" src={user.avatar.clone()} alt="avatar"/>
+                                                                        <img onclick={show_profile} class="w-8 h-8 rounded-full cursor-pointer" src={avatar_url(&user.name, avatar_style)} alt="avatar"/>
                                                                     }
                                                                 } else {
                                                                     html! {}
@@ -387,7 +1560,57 @@ impl Component for Chat {
                         
                         // Input Area
                         <div class="border-t border-gray-200 bg-white p-4 w-full">
+                            {
+                                match &self.composer_error {
+                                    Some(error) => html! {
+                                        <div class="mb-2 text-sm text-red-600">{error}</div>
+                                    },
+                                    None => html! {},
+                                }
+                            }
+                            if !self.attachments.is_empty() || self.preparing_images > 0 {
+                                <div class="flex gap-2 mb-2 overflow-x-auto">
+                                    {
+                                        self.attachments.iter().enumerate().map(|(i, image)| {
+                                            let remove = ctx.link().callback(move |_| Msg::RemoveAttachment(i));
+                                            html! {
+                                                <div class="relative flex-shrink-0">
+                                                    <img class="h-16 w-16 object-cover rounded-lg border border-gray-200" src={image.data_url.clone()} title={image.name.clone()}/>
+                                                    <button
+                                                        onclick={remove}
+                                                        class="absolute -top-1 -right-1 w-5 h-5 rounded-full bg-gray-700 text-white text-xs leading-5"
+                                                        title="Remove"
+                                                    >
+                                                        {"✕"}
+                                                    </button>
+                                                </div>
+                                            }
+                                        }).collect::<Html>()
+                                    }
+                                    {
+                                        (0..self.preparing_images).map(|_| html! {
+                                            <div class="flex-shrink-0 h-16 w-16 rounded-lg bg-gray-100 animate-pulse"></div>
+                                        }).collect::<Html>()
+                                    }
+                                </div>
+                            }
                             <div class="flex items-end gap-2 max-w-full">
+                                <input
+                                    ref={self.file_input.clone()}
+                                    type="file"
+                                    multiple=true
+                                    class="hidden"
+                                    onchange={ctx.link().callback(|_| Msg::FilesSelected)}
+                                />
+                                <button
+                                    onclick={ctx.link().callback(|_| Msg::PickFile)}
+                                    class="p-3 rounded-full hover:bg-gray-200 text-gray-500 hover:text-gray-700 transition-colors flex-shrink-0"
+                                    title="Send a file"
+                                >
+                                    <svg xmlns="http://www.w3.org/2000/svg" class="h-6 w-6" fill="none" viewBox="0 0 24 24" stroke="currentColor">
+                                        <path stroke-linecap="round" stroke-linejoin="round" stroke-width="2" d="M15.172 7l-6.586 6.586a2 2 0 102.828 2.828l6.414-6.586a4 4 0 00-5.656-5.656l-6.415 6.585a6 6 0 108.486 8.486L20.5 13" />
+                                    </svg>
+                                </button>
                                 <div class="relative flex-grow">
                                     <input 
                                         ref={self.chat_input.clone()} 
@@ -395,7 +1618,43 @@ impl Component for Chat {
                                         placeholder="Type a message..." 
                                         class="block w-full py-3 px-4 pr-12 bg-gray-100 rounded-full border border-gray-300 focus:border-purple-500 focus:ring-2 focus:ring-purple-200 focus:outline-none transition-all"
                                         onkeypress={onkeypress}
+                                        {onkeydown}
+                                        {oninput}
+                                        {onpaste}
                                     />
+                                    if !suggestions.is_empty() {
+                                        <div class="absolute left-0 bottom-14 w-full max-w-md bg-white shadow-lg rounded-lg border border-gray-200 overflow-hidden">
+                                            {
+                                                suggestions.iter().map(|command| {
+                                                    let name = command.name().to_string();
+                                                    let onclick = ctx.link().callback(move |_| Msg::CompleteCommand(Some(name.clone())));
+                                                    html! {
+                                                        <button {onclick} class="w-full text-left px-3 py-2 hover:bg-purple-50 flex justify-between items-baseline gap-4">
+                                                            <span class="font-mono text-sm text-purple-700">{command.usage()}</span>
+                                                            <span class="text-xs text-gray-500">{command.description()}</span>
+                                                        </button>
+                                                    }
+                                                }).collect::<Html>()
+                                            }
+                                        </div>
+                                    }
+                                    if !shortcodes.is_empty() {
+                                        <div class="absolute left-0 bottom-14 w-64 bg-white shadow-lg rounded-lg border border-gray-200 overflow-hidden">
+                                            {
+                                                shortcodes.iter().map(|(code, e)| {
+                                                    let value = emoji::with_tone(e, self.settings.skin_tone).to_string();
+                                                    let choice = value.clone();
+                                                    let onclick = ctx.link().callback(move |_| Msg::CompleteShortcode(Some(choice.clone())));
+                                                    html! {
+                                                        <button {onclick} class="w-full text-left px-3 py-1 hover:bg-purple-50 flex items-center gap-2">
+                                                            <span class="text-xl">{value}</span>
+                                                            <span class="font-mono text-sm text-gray-600">{format!(":{}:", code)}</span>
+                                                        </button>
+                                                    }
+                                                }).collect::<Html>()
+                                            }
+                                        </div>
+                                    }
                                     <div class="absolute right-2 bottom-2 flex space-x-1">
                                         <button 
                                             onclick={toggle_emoji}
@@ -409,24 +1668,16 @@ impl Component for Chat {
                                     
                                     {
                                         if self.show_emoji_picker {
-                                            let emojis = vec!["😊", "😂", "❤️", "👍", "🔥", "✨", "🎉", "🙏", "💯", "🤔"];
                                             html! {
-                                                <div class="absolute right-0 bottom-12 bg-white shadow-lg rounded-lg p-2 border border-gray-200 grid grid-cols-5 gap-1">
-                                                    {
-                                                        emojis.iter().map(|emoji| {
-                                                            let emoji_clone = emoji.to_string();
-                                                            let insert_emoji = ctx.link().callback(move |_| Msg::InsertEmoji(emoji_clone.clone()));
-                                                            html! {
-                                                                <button 
-                                                                    onclick={insert_emoji} 
-                                                                    class="w-8 h-8 text-xl hover:bg-gray-100 rounded"
-                                                                >
-                                                                    {emoji}
-                                                                </button>
-                                                            }
-                                                        }).collect::<Html>()
-                                                    }
-                                                </div>
+                                                <EmojiPicker
+                                                    recents={self.recent_emoji.clone()}
+                                                    skin_tone={self.settings.skin_tone}
+                                                    onselect={ctx.link().callback(Msg::InsertEmoji)}
+                                                    onskintone={ctx.link().callback(Msg::SetSkinTone)}
+                                                    packs={self.emoji_packs.clone()}
+                                                    onsticker={ctx.link().callback(Msg::SendSticker)}
+                                                    onclose={ctx.link().callback(|_| Msg::ToggleEmojiPicker)}
+                                                />
                                             }
                                         } else {
                                             html! {}
@@ -442,13 +1693,544 @@ impl Component for Chat {
                                     </svg>
                                 </button>
                             </div>
-                            <div class="text-xs text-gray-500 mt-2 text-center">
-                                {"Pro tip: Type .gif at the end of your message to show an image!"}
-                            </div>
+                            if self.keyboard_height.is_none() {
+                                <div class="hidden sm:block text-xs text-gray-500 mt-2 text-center">
+                                    {"Pro tip: Send a link ending in .gif, .png or .mp4 to show it inline!"}
+                                </div>
+                            }
                         </div>
                     </div>
                 </div>
+                {
+                    match self.lightbox {
+                        Some(index) => html! {
+                            <Lightbox
+                                items={media}
+                                {index}
+                                onnavigate={open_lightbox}
+                                onclose={ctx.link().callback(|_| Msg::CloseLightbox)}
+                            />
+                        },
+                        None => html! {},
+                    }
+                }
+                if let Some(name) = &self.profile {
+                    <ProfileCard
+                        name={name.clone()}
+                        presence={self.users.iter().find(|u| &u.name == name).map(|u| u.presence.clone())}
+                        seen={self.last_seen.get(name).cloned()}
+                        message_count={self.history.count_by(name)}
+                        ignored={self.settings.is_ignored(name)}
+                        is_self={*name == self.username}
+                        {avatar_style}
+                        now={self.now}
+                        ondirect={ctx.link().callback(Msg::StartDirect)}
+                        onmention={ctx.link().callback(Msg::Mention)}
+                        onignore={ctx.link().callback(Msg::ToggleIgnore)}
+                        onclose={ctx.link().callback(|_| Msg::ShowProfile(None))}
+                    />
+                }
             </div>
         }
     }
-}
\ No newline at end of file
+}
+
+impl Chat {
+    fn register(wss: &WebsocketService) {
+        store::dispatch(Action::SetConnection(Connection::Connecting));
+        let request = RegisterRequest {
+            nick: store::state().user.username.clone(),
+            resume_token: session::remembered().and_then(|identity| identity.resume_token),
+            token: session::auth_token().map(|auth| auth.token),
+        };
+
+        let message = WebSocketMessage {
+            message_type: MsgTypes::Register,
+            data: Some(serde_json::to_string(&request).unwrap()),
+            data_array: None,
+        };
+
+        if let Ok(_) = wss
+            .tx
+            .clone()
+            .try_send(serde_json::to_string(&message).unwrap())
+        {
+            log::debug!("message sent successfully");
+        }
+    }
+
+    fn push_message(&mut self, mut message: MessageData) {
+        message.id = self.next_message_id;
+        self.next_message_id += 1;
+        self.messages.push(message);
+    }
+
+    fn push_roster_events(&mut self, events: Vec<RosterEvent>) {
+        for event in events {
+            self.push_message(MessageData {
+                kind: EntryKind::Roster,
+                ..MessageData::system(event.describe())
+            });
+        }
+    }
+
+    fn schedule_roster_flush(&mut self, ctx: &Context<Self>) {
+        self.roster_timer = self.roster.next_flush_in(js_sys::Date::now()).map(|ms| {
+            let link = ctx.link().clone();
+            Timeout::new(ms, move || link.send_message(Msg::FlushRoster))
+        });
+    }
+
+    fn watch_idle(&mut self, ctx: &Context<Self>) {
+        let minutes = self.settings.auto_away_minutes;
+        self.idle_watcher =
+            (minutes > 0).then(|| IdleWatcher::new(minutes, ctx.link().callback(Msg::IdleChanged)));
+        if self.idle_watcher.is_none() && self.idle {
+            self.idle = false;
+            self.send_presence();
+        }
+    }
+
+    /// What others should see: the chosen status, busy during do not
+    /// disturb, or away while idle.
+    fn effective_presence(&self) -> Presence {
+        let mut presence = self.presence.clone();
+        if self.dnd_active && matches!(presence.status, Status::Online | Status::Away) {
+            presence.status = Status::Busy;
+        } else if self.idle && presence.status == Status::Online {
+            presence.status = Status::Away;
+        }
+        presence
+    }
+
+    /// Follows do not disturb turning on or off, by hand or by schedule,
+    /// and sums up what was missed once it ends.
+    fn update_dnd(&mut self) {
+        let active = self.settings.dnd.is_active();
+        if active == self.dnd_active {
+            return;
+        }
+        self.dnd_active = active;
+        self.send_presence();
+        if !active && self.missed_mentions > 0 {
+            let count = std::mem::take(&mut self.missed_mentions);
+            self.push_message(MessageData::system(format!(
+                "You missed {} {} while Do Not Disturb was on",
+                count,
+                if count == 1 { "mention" } else { "mentions" }
+            )));
+        }
+    }
+
+    fn set_presence(&mut self, mut presence: Presence) {
+        presence.text = presence
+            .text
+            .map(|text| emoji::replace_shortcodes(text.trim(), self.settings.skin_tone))
+            .filter(|text| !text.is_empty());
+        self.presence = presence;
+        self.send_presence();
+    }
+
+    fn send_presence(&self) {
+        self.send(
+            MsgTypes::Presence,
+            serde_json::to_string(&self.effective_presence()).unwrap(),
+        );
+    }
+
+    fn request_rename(&mut self, name: &str) -> Result<(), String> {
+        let name = username::validate(name)?;
+        if name == self.username {
+            return Err(format!("You're already called {}", name));
+        }
+        self.rename_error = None;
+        self.send(MsgTypes::Rename, name);
+        Ok(())
+    }
+
+    /// Moves everything attributed to `from` over to `to`, keeping the old
+    /// name on past messages so they still read as they were sent.
+    fn apply_rename(&mut self, from: &str, to: &str) {
+        for message in &mut self.messages {
+            let authored = matches!(message.kind, EntryKind::Chat | EntryKind::File(_));
+            if authored && message.from == from {
+                message.sent_as.get_or_insert_with(|| from.to_string());
+                message.from = to.to_string();
+            }
+            if message.to.as_deref() == Some(from) {
+                message.to = Some(to.to_string());
+            }
+        }
+        for user in &mut self.users {
+            if user.name == from {
+                user.name = to.to_string();
+            }
+        }
+        self.roster.rename(from, to);
+        self.last_seen.rename(from, to);
+        self.last_seen.save();
+        self.history.rename(from, to);
+        self.history.save();
+        if self.settings.is_ignored(from) {
+            // Otherwise a rename would be a way around being ignored.
+            let mut settings = self.settings.clone();
+            settings.ignored.retain(|n| n != from);
+            if !settings.is_ignored(to) {
+                settings.ignored.push(to.to_string());
+            }
+            store::dispatch(Action::UpdateSettings(settings));
+        }
+        if self.username == from {
+            session::update_remembered(|identity| identity.username = to.to_string());
+            store::dispatch(Action::SetUsername(to.to_string()));
+            self.show_settings = false;
+        }
+        self.push_message(MessageData::system(format!("{} is now known as {}", from, to)));
+    }
+
+    /// Swaps the sign-in token for a fresh one before it expires, so the next
+    /// reconnect can still present a valid token.
+    fn schedule_token_refresh(&mut self, ctx: &Context<Self>) {
+        self.refresh_timer = session::auth_token().map(|token| {
+            let link = ctx.link().clone();
+            Timeout::new(token.refresh_delay_ms(), move || {
+                link.send_future(async move { Msg::TokenRefreshed(auth::refresh(&token).await) });
+            })
+        });
+    }
+
+    /// Describes anything that would be lost by leaving now, if there is any.
+    fn outbox_warning(&self) -> Option<String> {
+        let mut unsent = vec![];
+        if !self.composer_text.trim().is_empty() {
+            unsent.push("an unsent message".to_string());
+        }
+        let images = self.attachments.len() + self.preparing_images;
+        if images > 0 {
+            unsent.push(format!("{} attached image(s)", images));
+        }
+        let uploads = self
+            .outgoing
+            .values()
+            .filter(|t| matches!(t.state, TransferState::InProgress | TransferState::Paused))
+            .count();
+        if uploads > 0 {
+            unsent.push(format!("{} unfinished file upload(s)", uploads));
+        }
+
+        if unsent.is_empty() {
+            None
+        } else {
+            Some(format!(
+                "You have {}. Log out anyway and discard them?",
+                unsent.join(", ")
+            ))
+        }
+    }
+
+    fn send(&self, message_type: MsgTypes, data: String) {
+        let message = WebSocketMessage {
+            message_type,
+            data: Some(data),
+            data_array: None,
+        };
+        if let Err(e) = self
+            .wss
+            .tx
+            .clone()
+            .try_send(serde_json::to_string(&message).unwrap())
+        {
+            log::debug!("error sending to channel: {:?}", e);
+        }
+    }
+
+    fn send_files(&mut self, ctx: &Context<Self>, files: Vec<File>) {
+        for file in files {
+            if file.size() > MAX_FILE_SIZE {
+                self.composer_error = Some(format!(
+                    "{} is too large to send (limit {})",
+                    file.name(),
+                    file_transfer::format_size(MAX_FILE_SIZE)
+                ));
+                continue;
+            }
+            let loaded = ctx.link().callback(Msg::FileLoaded);
+            spawn_local(async move {
+                let result = gloo::file::futures::read_as_bytes(&file)
+                    .await
+                    .map_err(|e| format!("Could not read {}: {}", file.name(), e))
+                    .and_then(|data| {
+                        OutgoingTransfer::new(file.name(), file.raw_mime_type(), data)
+                    });
+                loaded.emit(result);
+            });
+        }
+    }
+
+    fn run_command(&mut self, action: CommandAction) -> Result<(), String> {
+        match action {
+            CommandAction::Send(text) => self.send(MsgTypes::Message, text),
+            CommandAction::Emote(text) => self.send(MsgTypes::Emote, text),
+            CommandAction::Rename(name) => self.request_rename(&name)?,
+            CommandAction::Away(reason) => self.set_presence(Presence {
+                status: Status::Away,
+                text: reason,
+            }),
+            CommandAction::Direct { to, text } => {
+                if !self.users.iter().any(|u| u.name == to) {
+                    // The server doesn't hold messages, so keep the text until they're back.
+                    return Err(match self.last_seen.get(&to) {
+                        Some(_) => format!("{} is offline; send this once they're back", to),
+                        None => format!("{} is not online", to),
+                    });
+                }
+                let direct = DirectMessage { to, message: text };
+                self.send(MsgTypes::Direct, serde_json::to_string(&direct).unwrap());
+            }
+            CommandAction::ClearTimeline => {
+                self.messages.clear();
+                self.lightbox = None;
+            }
+            CommandAction::Help => {
+                let lines: Vec<String> = commands::with_registry(|r| {
+                    r.commands()
+                        .iter()
+                        .map(|c| format!("{} — {}", c.usage(), c.description()))
+                        .collect()
+                });
+                self.push_message(MessageData::system(format!(
+                    "Available commands:\n{}",
+                    lines.join("\n")
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    fn send_file_control(&self, control: &FileControl) {
+        self.send(MsgTypes::FileControl, serde_json::to_string(control).unwrap());
+    }
+
+    /// Streams the chunks of an outgoing file from `from_index` onwards. If
+    /// a stream is already running it is rewound instead. Progress comes
+    /// from the server's acknowledgements in `Msg::HandleBytes`.
+    fn stream_chunks(&self, id: u64, from_index: u32) {
+        let transfer = match self.outgoing.get(&id) {
+            Some(transfer) => transfer,
+            None => return,
+        };
+        let generation = match transfer.cursor.start(from_index) {
+            Some(generation) => generation,
+            None => return,
+        };
+        let data = transfer.data.clone();
+        let cursor = transfer.cursor.clone();
+        let total = transfer.offer.total_chunks;
+        let mut tx = self.wss.bytes_tx.clone();
+        spawn_local(async move {
+            while let Some(index) = cursor.advance(generation, total) {
+                let frame = file_transfer::chunk_frame(id, &data, index);
+                if tx.send(frame).await.is_err() {
+                    // The socket went away; `Msg::Reconnect` picks this up.
+                    return;
+                }
+            }
+        });
+    }
+
+    fn view_file_card(&self, ctx: &Context<Self>, id: u64, dark: bool) -> Html {
+        let transfer = match self.outgoing.get(&id) {
+            Some(transfer) => transfer.view(),
+            None => match self.incoming.get(&id) {
+                Some(transfer) => transfer.view(),
+                None => return html! {},
+            },
+        };
+        html! {
+            <FileCard {transfer} {dark} oncancel={ctx.link().callback(Msg::CancelTransfer)}/>
+        }
+    }
+
+    /// Shows a desktop notification for the message at `index` if the tab
+    /// is in the background and the notification settings call for one.
+    fn notify(&mut self, ctx: &Context<Self>, index: usize) {
+        if !in_background() {
+            return;
+        }
+        let message = &self.messages[index];
+        if self.settings.is_ignored(&message.from) {
+            return;
+        }
+        let direct = message.to.as_deref() == Some(self.username.as_str());
+        if self.dnd_active && !self.settings.dnd.allows(&message.from, direct) {
+            return;
+        }
+        let trigger = notifications::trigger(
+            &self.settings.notifications,
+            self.active_room.as_deref(),
+            &self.username,
+            &message.from,
+            message.to.as_deref(),
+            &message.message,
+        );
+        let title = match trigger {
+            None => return,
+            Some(Trigger::Direct) => format!("{} (private)", message.from),
+            Some(Trigger::Mention) => format!("{} mentioned you", message.from),
+            Some(Trigger::Keyword(keyword)) => format!("{} mentioned \"{}\"", message.from, keyword),
+            Some(Trigger::Message) => match &self.active_room {
+                Some(room) => format!("{} in #{}", message.from, room),
+                None => message.from.clone(),
+            },
+        };
+        let id = message.id;
+        let shown = DesktopNotification::show(
+            &title,
+            &if message.emote {
+                format!("* {} {}", message.from, notifications::preview(&message.message))
+            } else {
+                notifications::preview(&message.message)
+            },
+            &avatar_url(&message.from, self.settings.avatar_style),
+            ctx.link().callback(move |_| Msg::JumpToMessage(id)),
+        );
+        if let Some(notification) = shown {
+            self.notifications.push(notification);
+            if self.notifications.len() > MAX_NOTIFICATIONS {
+                self.notifications.remove(0);
+            }
+        }
+    }
+
+    fn highlight_class(&self, id: u64) -> Classes {
+        if self.highlighted == Some(id) {
+            classes!("rounded-2xl", "ring-2", "ring-amber-400", "ring-offset-2")
+        } else {
+            classes!()
+        }
+    }
+
+    fn profile_callback(&self, ctx: &Context<Self>, name: &str) -> Callback<MouseEvent> {
+        let name = name.to_string();
+        ctx.link().callback(move |_| Msg::ShowProfile(Some(name.clone())))
+    }
+
+    fn view_offline_users(&self, ctx: &Context<Self>) -> Html {
+        let online: Vec<&str> = self.users.iter().map(|u| u.name.as_str()).collect();
+        let offline = self.last_seen.offline(&online);
+        if offline.is_empty() {
+            return html! {};
+        }
+        let avatar_style = self.settings.avatar_style;
+        html! {
+            <>
+                <h3 class="px-3 pt-4 pb-1 text-xs font-semibold uppercase tracking-wide text-gray-400">
+                    {format!("Offline — {}", offline.len())}
+                </h3>
+                <ul class="divide-y">
+                    {
+                        for offline.into_iter().map(|u| {
+                            let name = u.name.clone();
+                            let onclick = ctx.link().callback(move |e: MouseEvent| {
+                                e.stop_propagation();
+                                Msg::StartDirect(name.clone())
+                            });
+                            html! {
+                                <li onclick={self.profile_callback(ctx, &u.name)} class="p-3 hover:bg-gray-50 cursor-pointer group">
+                                    <div class="flex items-center space-x-3">
+                                        <img class="w-10 h-10 rounded-full opacity-50 grayscale" src={avatar_url(&u.name, avatar_style)} alt="avatar"/>
+                                        <div class="flex-1 min-w-0">
+                                            <div class="text-sm font-medium text-gray-500 truncate">{u.name.clone()}</div>
+                                            <div class="text-xs text-gray-400">
+                                                {format!("last seen {}", last_seen::ago(u.last_seen, self.now))}
+                                            </div>
+                                        </div>
+                                        <button
+                                            {onclick}
+                                            class="invisible group-hover:visible text-xs text-purple-600 hover:text-purple-800"
+                                            title={format!("Send {} a direct message", u.name)}
+                                        >
+                                            {"Message"}
+                                        </button>
+                                    </div>
+                                </li>
+                            }
+                        })
+                    }
+                </ul>
+            </>
+        }
+    }
+
+    fn view_lookalike_marker(&self, name: &str) -> Html {
+        user_list::lookalike_marker(self.lookalikes.get(name).map(String::as_str))
+    }
+
+    fn user_list_entries(&self) -> Vec<UserListEntry> {
+        let last_active = self.history.last_active();
+        self.users
+            .iter()
+            .map(|u| UserListEntry {
+                name: u.name.clone(),
+                presence: u.presence.clone(),
+                account: u.account,
+                last_active: last_active.get(u.name.as_str()).copied(),
+                lookalike_of: self.lookalikes.get(&u.name).cloned(),
+            })
+            .collect()
+    }
+
+    fn view_text(&self, text: &str) -> Html {
+        custom_emoji::segments(text, &self.emoji_packs)
+            .into_iter()
+            .map(|segment| match segment {
+                Segment::Text(text) => html! { {text.to_string()} },
+                Segment::Emoji { name, url } => html! {
+                    <img class="inline-block h-6 w-6 align-text-bottom" src={url.to_string()} alt={format!(":{}:", name)} title={format!(":{}:", name)}/>
+                },
+            })
+            .collect::<Html>()
+    }
+
+    fn media_items(&self) -> Vec<MediaItem> {
+        self.messages
+            .iter()
+            .filter(|m| m.kind == EntryKind::Chat && !m.emote && !self.settings.is_ignored(&m.from))
+            .filter_map(|m| {
+                media_kind(&m.message).map(|kind| MediaItem {
+                    kind,
+                    url: m.message.trim().to_string(),
+                    from: m.from.clone(),
+                    message_id: m.id,
+                })
+            })
+            .collect()
+    }
+}
+
+fn message_id(id: u64) -> String {
+    format!("message-{}", id)
+}
+
+fn in_background() -> bool {
+    web_sys::window()
+        .and_then(|w| w.document())
+        .map(|d| d.hidden() || !d.has_focus().unwrap_or(true))
+        .unwrap_or(false)
+}
+
+/// Whether `text` would show the command or shortcode autocomplete.
+fn autocomplete_active(text: &str) -> bool {
+    text.starts_with('/') || emoji::shortcode_query(text).is_some()
+}
+
+fn files_from(list: Option<FileList>) -> Vec<File> {
+    let list = match list {
+        Some(list) => list,
+        None => return vec![],
+    };
+    (0..list.length())
+        .filter_map(|i| list.get(i))
+        .map(File::from)
+        .collect()
+}
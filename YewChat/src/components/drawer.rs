@@ -0,0 +1,176 @@
+use yew::prelude::*;
+
+#[derive(Properties, PartialEq)]
+pub struct DrawerToggleProps {
+    pub open: bool,
+    /// Shown as a badge on the button.
+    pub count: usize,
+    pub ontoggle: Callback<()>,
+}
+
+#[function_component(DrawerToggle)]
+pub fn drawer_toggle(props: &DrawerToggleProps) -> Html {
+    let onclick = {
+        let ontoggle = props.ontoggle.clone();
+        Callback::from(move |_| ontoggle.emit(()))
+    };
+    html! {
+        <button
+            {onclick}
+            class="md:hidden relative rounded-lg p-1 hover:bg-white/10"
+            title="Show who's online"
+            aria-label="Show who's online"
+            aria-expanded={props.open.to_string()}
+        >
+            <svg xmlns="http://www.w3.org/2000/svg" class="h-6 w-6" viewBox="0 0 20 20" fill="currentColor">
+                <path d="M13 6a3 3 0 11-6 0 3 3 0 016 0zM18 8a2 2 0 11-4 0 2 2 0 014 0zM14 15a4 4 0 00-8 0v3h8v-3zM6 8a2 2 0 11-4 0 2 2 0 014 0zM16 18v-3a5.972 5.972 0 00-.75-2.906A3.005 3.005 0 0119 15v3h-3zM4.75 12.094A5.973 5.973 0 004 15v3H1v-3a3 3 0 013.75-2.906z" />
+            </svg>
+            <span class="absolute -top-1 -right-1 bg-purple-500 text-white text-[10px] rounded-full px-1">{props.count}</span>
+        </button>
+    }
+}
+
+#[derive(Properties, PartialEq)]
+pub struct DrawerProps {
+    pub open: bool,
+    /// Called when the backdrop is tapped.
+    pub onclose: Callback<()>,
+    pub children: Children,
+}
+
+/// Sidebar that slides in over the chat on small screens and is always shown
+/// from the `md` breakpoint up.
+#[function_component(Drawer)]
+pub fn drawer(props: &DrawerProps) -> Html {
+    let onclose = {
+        let onclose = props.onclose.clone();
+        Callback::from(move |_| onclose.emit(()))
+    };
+    html! {
+        <>
+            if props.open {
+                <div class="md:hidden fixed inset-0 z-30 bg-black/30" onclick={onclose}></div>
+            }
+            <div class={classes!(
+                "fixed", "inset-y-0", "left-0", "z-40", "w-72", "bg-white", "shadow-md", "transform", "transition-transform",
+                "md:static", "md:z-auto", "md:w-64", "md:translate-x-0",
+                if props.open { "translate-x-0" } else { "-translate-x-full" }
+            )}>
+                { for props.children.iter() }
+            </div>
+        </>
+    }
+}
+
+#[cfg(all(test, target_arch = "wasm32"))]
+mod tests {
+    use wasm_bindgen_test::*;
+    use web_sys::{Element, HtmlElement, HtmlIFrameElement};
+
+    use super::*;
+    use crate::test_support::{is_displayed, query, render, render_in, settle, viewport};
+
+    wasm_bindgen_test_configure!(run_in_browser);
+
+    const STYLES: &str = "
+        .fixed { position: fixed; }
+        .inset-0 { inset: 0; }
+        .inset-y-0 { top: 0; bottom: 0; }
+        .left-0 { left: 0; }
+        .w-72 { width: 18rem; }
+        .translate-x-0 { transform: translateX(0); }
+        .-translate-x-full { transform: translateX(-100%); }
+        @media (min-width: 768px) {
+            .md\\:hidden { display: none; }
+            .md\\:static { position: static; }
+            .md\\:w-64 { width: 16rem; }
+            .md\\:translate-x-0 { transform: translateX(0); }
+        }
+    ";
+
+    #[function_component(Harness)]
+    fn harness() -> Html {
+        let open = use_state(|| false);
+        let ontoggle = {
+            let open = open.clone();
+            Callback::from(move |_| open.set(!*open))
+        };
+        html! {
+            <>
+                <DrawerToggle open={*open} count={2} ontoggle={ontoggle.clone()}/>
+                <Drawer open={*open} onclose={ontoggle}>{"alice"}</Drawer>
+            </>
+        }
+    }
+
+    #[derive(Properties, PartialEq)]
+    struct FixedProps {
+        open: bool,
+    }
+
+    #[function_component(Fixed)]
+    fn fixed(props: &FixedProps) -> Html {
+        html! {
+            <>
+                <DrawerToggle open={props.open} count={2} ontoggle={Callback::noop()}/>
+                <Drawer open={props.open} onclose={Callback::noop()}>{"alice"}</Drawer>
+            </>
+        }
+    }
+
+    fn panel(root: &Element) -> HtmlElement {
+        query(root, ".w-72").expect("drawer panel")
+    }
+
+    async fn fixed_in(iframe: &HtmlIFrameElement, open: bool) -> Element {
+        render_in::<Fixed>(iframe, FixedProps { open }).await
+    }
+
+    #[wasm_bindgen_test]
+    async fn toggle_opens_and_backdrop_closes() {
+        let root = render::<Harness>(()).await;
+
+        let toggle = query(&root, "button").unwrap();
+        assert_eq!(toggle.get_attribute("aria-expanded").as_deref(), Some("false"));
+        assert!(panel(&root).class_list().contains("-translate-x-full"));
+        assert!(query(&root, ".bg-black\\/30").is_none());
+
+        toggle.click();
+        settle().await;
+        assert_eq!(toggle.get_attribute("aria-expanded").as_deref(), Some("true"));
+        assert!(panel(&root).class_list().contains("translate-x-0"));
+        assert!(!panel(&root).class_list().contains("-translate-x-full"));
+
+        query(&root, ".bg-black\\/30").expect("backdrop").click();
+        settle().await;
+        assert_eq!(toggle.get_attribute("aria-expanded").as_deref(), Some("false"));
+        assert!(query(&root, ".bg-black\\/30").is_none());
+    }
+
+    #[wasm_bindgen_test]
+    async fn narrow_viewport_slides_the_drawer_in() {
+        let iframe = viewport(375, STYLES).await;
+
+        let closed = fixed_in(&iframe, false).await;
+        assert!(is_displayed(&iframe, &query(&closed, "button").unwrap()));
+        assert!(panel(&closed).get_bounding_client_rect().right() <= 0.0);
+        closed.remove();
+
+        let open = fixed_in(&iframe, true).await;
+        let rect = panel(&open).get_bounding_client_rect();
+        assert_eq!(rect.left(), 0.0);
+        assert_eq!(rect.width(), 288.0);
+        assert!(is_displayed(&iframe, &query(&open, ".bg-black\\/30").unwrap()));
+    }
+
+    #[wasm_bindgen_test]
+    async fn wide_viewport_always_shows_the_drawer() {
+        let iframe = viewport(1024, STYLES).await;
+
+        let root = fixed_in(&iframe, false).await;
+        assert!(!is_displayed(&iframe, &query(&root, "button").unwrap()));
+        let rect = panel(&root).get_bounding_client_rect();
+        assert!(rect.left() >= 0.0);
+        assert_eq!(rect.width(), 256.0);
+    }
+}
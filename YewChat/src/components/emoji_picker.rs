@@ -0,0 +1,341 @@
+use web_sys::HtmlInputElement;
+use yew::prelude::*;
+
+use crate::services::custom_emoji::EmojiPack;
+use crate::services::emoji::{self, SkinTone, CATEGORIES};
+
+#[derive(Properties, PartialEq)]
+pub struct EmojiPickerProps {
+    pub recents: Vec<String>,
+    pub skin_tone: SkinTone,
+    #[prop_or_default]
+    pub packs: Vec<EmojiPack>,
+    pub onselect: Callback<String>,
+    pub onskintone: Callback<SkinTone>,
+    /// Called with the image data URL when a sticker is picked.
+    pub onsticker: Callback<String>,
+    /// Called when the backdrop behind the small-screen bottom sheet is tapped.
+    #[prop_or_default]
+    pub onclose: Callback<()>,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Tab {
+    Category(usize),
+    Custom,
+    Stickers,
+}
+
+#[function_component(EmojiPicker)]
+pub fn emoji_picker(props: &EmojiPickerProps) -> Html {
+    let query = use_state(String::new);
+    let tab = use_state(|| Tab::Category(0));
+
+    let oninput = {
+        let query = query.clone();
+        Callback::from(move |e: InputEvent| {
+            let input: HtmlInputElement = e.target_unchecked_into();
+            query.set(input.value());
+        })
+    };
+
+    let tone = props.skin_tone;
+
+    let has_custom = props.packs.iter().any(|p| !p.emoji.is_empty());
+    let has_stickers = props.packs.iter().any(|p| !p.stickers.is_empty());
+    let searching = !query.trim().is_empty();
+
+    let grid = match *tab {
+        _ if searching => {
+            let needle = query.trim().trim_matches(':').to_lowercase();
+            let custom = props
+                .packs
+                .iter()
+                .flat_map(|p| p.emoji.iter())
+                .filter(|(name, _)| name.contains(&needle))
+                .map(|(name, url)| custom_button(props, name, url));
+            let builtin = emoji::search(&query)
+                .into_iter()
+                .map(|e| builtin_button(props, e));
+            html! {
+                <>
+                    <div class="px-2 pt-2 text-xs font-semibold text-gray-500">{"Search results"}</div>
+                    <div class="h-48 overflow-y-auto px-2 pb-2 grid grid-cols-8 gap-1 content-start">
+                        { for custom }
+                        { for builtin }
+                    </div>
+                </>
+            }
+        }
+        Tab::Category(index) => {
+            let (group, label, _) = CATEGORIES[index];
+            html! {
+                <>
+                    <div class="px-2 pt-2 text-xs font-semibold text-gray-500">{label}</div>
+                    <div class="h-48 overflow-y-auto px-2 pb-2 grid grid-cols-8 gap-1 content-start">
+                        { for emoji::in_category(group).into_iter().map(|e| builtin_button(props, e)) }
+                    </div>
+                </>
+            }
+        }
+        Tab::Custom => html! {
+            <div class="h-56 overflow-y-auto px-2 pb-2">
+                {
+                    props.packs.iter().filter(|p| !p.emoji.is_empty()).map(|pack| html! {
+                        <>
+                            <div class="pt-2 text-xs font-semibold text-gray-500">{pack.name.clone()}</div>
+                            <div class="grid grid-cols-8 gap-1">
+                                { for pack.emoji.iter().map(|(name, url)| custom_button(props, name, url)) }
+                            </div>
+                        </>
+                    }).collect::<Html>()
+                }
+            </div>
+        },
+        Tab::Stickers => html! {
+            <div class="h-56 overflow-y-auto px-2 pb-2">
+                {
+                    props.packs.iter().filter(|p| !p.stickers.is_empty()).map(|pack| html! {
+                        <>
+                            <div class="pt-2 text-xs font-semibold text-gray-500">{pack.name.clone()}</div>
+                            <div class="grid grid-cols-3 gap-2">
+                                {
+                                    for pack.stickers.iter().map(|(name, url)| {
+                                        let onsticker = props.onsticker.clone();
+                                        let sticker = url.clone();
+                                        html! {
+                                            <button
+                                                onclick={Callback::from(move |_| onsticker.emit(sticker.clone()))}
+                                                class="aspect-square p-1 rounded hover:bg-gray-100"
+                                                title={name.clone()}
+                                            >
+                                                <img class="w-full h-full object-contain" src={url.clone()} alt={name.clone()}/>
+                                            </button>
+                                        }
+                                    })
+                                }
+                            </div>
+                        </>
+                    }).collect::<Html>()
+                }
+            </div>
+        },
+    };
+    let tab_button = |target: Tab, icon: &'static str, title: &'static str| {
+        let tab_handle = tab.clone();
+        html! {
+            <button
+                onclick={Callback::from(move |_| tab_handle.set(target))}
+                class={classes!("w-8", "h-8", "rounded", "hover:bg-gray-100", if *tab == target { "bg-purple-100" } else { "" })}
+                {title}
+            >
+                {icon}
+            </button>
+        }
+    };
+
+    let onclose = {
+        let onclose = props.onclose.clone();
+        Callback::from(move |_| onclose.emit(()))
+    };
+
+    // A bottom sheet on small screens, a popover above the composer otherwise.
+    html! {
+        <>
+            <div class="md:hidden fixed inset-0 z-30 bg-black/30" onclick={onclose.clone()}></div>
+            <div class="fixed inset-x-0 bottom-0 z-40 max-h-[60vh] rounded-t-2xl md:absolute md:inset-x-auto md:right-0 md:bottom-12 md:z-10 md:w-80 md:max-h-none md:rounded-lg bg-white shadow-lg border border-gray-200 flex flex-col">
+                <button onclick={onclose} class="md:hidden self-center my-2 h-1.5 w-10 rounded-full bg-gray-300" aria-label="Close"></button>
+                <div class="p-2 border-b flex items-center gap-2">
+                    <input
+                        {oninput}
+                        value={(*query).clone()}
+                        type="text"
+                        placeholder="Search emoji"
+                        class="flex-1 px-2 py-1 text-sm bg-gray-100 rounded border border-gray-200 focus:outline-none focus:border-purple-400"
+                    />
+                    <div class="flex">
+                        {
+                            SkinTone::ALL.iter().map(|t| {
+                                let t = *t;
+                                let onskintone = props.onskintone.clone();
+                                html! {
+                                    <button
+                                        onclick={Callback::from(move |_| onskintone.emit(t))}
+                                        class={classes!("w-6", "h-6", "text-sm", "rounded", if t == tone { "bg-purple-100" } else { "" })}
+                                        title="Skin tone"
+                                    >
+                                        {t.swatch()}
+                                    </button>
+                                }
+                            }).collect::<Html>()
+                        }
+                    </div>
+                </div>
+                if !searching && matches!(*tab, Tab::Category(_)) && !props.recents.is_empty() {
+                    <div class="px-2 pt-2">
+                        <div class="text-xs font-semibold text-gray-500 mb-1">{"Recently used"}</div>
+                        <div class="flex flex-wrap">
+                            { for props.recents.iter().map(|e| text_button(props, e.clone(), e.clone())) }
+                        </div>
+                    </div>
+                }
+                {grid}
+                if !searching {
+                    <div class="flex flex-wrap justify-between border-t px-1 py-1">
+                        { for CATEGORIES.iter().enumerate().map(|(i, (_, label, icon))| tab_button(Tab::Category(i), icon, label)) }
+                        if has_custom {
+                            {tab_button(Tab::Custom, "⭐", "Custom emoji")}
+                        }
+                        if has_stickers {
+                            {tab_button(Tab::Stickers, "🖼️", "Stickers")}
+                        }
+                    </div>
+                }
+            </div>
+        </>
+    }
+}
+
+fn text_button(props: &EmojiPickerProps, value: String, title: String) -> Html {
+    let onselect = props.onselect.clone();
+    let emoji = value.clone();
+    html! {
+        <button
+            onclick={Callback::from(move |_| onselect.emit(emoji.clone()))}
+            class="w-8 h-8 text-xl hover:bg-gray-100 rounded"
+            {title}
+        >
+            {value}
+        </button>
+    }
+}
+
+fn builtin_button(props: &EmojiPickerProps, e: &'static emojis::Emoji) -> Html {
+    let title = e
+        .shortcode()
+        .map(|c| format!(":{}:", c))
+        .unwrap_or_else(|| e.name().to_string());
+    text_button(props, emoji::with_tone(e, props.skin_tone).to_string(), title)
+}
+
+/// Custom emoji insert their `:name:` shortcode, which renders as the image.
+fn custom_button(props: &EmojiPickerProps, name: &str, url: &str) -> Html {
+    let code = format!(":{}:", name);
+    let onclick = {
+        let onselect = props.onselect.clone();
+        let code = code.clone();
+        Callback::from(move |_| onselect.emit(code.clone()))
+    };
+    html! {
+        <button
+            {onclick}
+            class="w-8 h-8 p-1 hover:bg-gray-100 rounded"
+            title={code}
+        >
+            <img class="w-full h-full object-contain" src={url.to_string()} alt={name.to_string()}/>
+        </button>
+    }
+}
+
+#[cfg(all(test, target_arch = "wasm32"))]
+mod tests {
+    use std::cell::Cell;
+    use std::rc::Rc;
+
+    use wasm_bindgen_test::*;
+    use web_sys::{Element, HtmlElement, HtmlIFrameElement};
+
+    use super::*;
+    use crate::test_support::{is_displayed, query, render, render_in, settle, viewport};
+
+    wasm_bindgen_test_configure!(run_in_browser);
+
+    const STYLES: &str = "
+        .relative { position: relative; }
+        .h-96 { height: 24rem; }
+        .fixed { position: fixed; }
+        .inset-0 { inset: 0; }
+        .inset-x-0 { left: 0; right: 0; }
+        .bottom-0 { bottom: 0; }
+        @media (min-width: 768px) {
+            .md\\:hidden { display: none; }
+            .md\\:absolute { position: absolute; }
+            .md\\:inset-x-auto { left: auto; right: auto; }
+            .md\\:right-0 { right: 0; }
+            .md\\:bottom-12 { bottom: 3rem; }
+            .md\\:w-80 { width: 20rem; }
+        }
+    ";
+
+    fn props(onclose: Callback<()>) -> EmojiPickerProps {
+        EmojiPickerProps {
+            recents: vec![],
+            skin_tone: SkinTone::default(),
+            packs: vec![],
+            onselect: Callback::noop(),
+            onskintone: Callback::noop(),
+            onsticker: Callback::noop(),
+            onclose,
+        }
+    }
+
+    #[function_component(Composer)]
+    fn composer() -> Html {
+        // Stands in for the composer the picker pops up from.
+        html! {
+            <div class="relative h-96">
+                <EmojiPicker ..props(Callback::noop())/>
+            </div>
+        }
+    }
+
+    fn sheet(root: &Element) -> HtmlElement {
+        query(root, ".rounded-t-2xl").expect("picker")
+    }
+
+    async fn picker_in(iframe: &HtmlIFrameElement) -> Element {
+        render_in::<Composer>(iframe, ()).await
+    }
+
+    #[wasm_bindgen_test]
+    async fn narrow_viewport_shows_a_bottom_sheet() {
+        let iframe = viewport(375, STYLES).await;
+        let root = picker_in(&iframe).await;
+
+        let rect = sheet(&root).get_bounding_client_rect();
+        assert_eq!(rect.left(), 0.0);
+        assert_eq!(rect.width(), 375.0);
+        assert_eq!(rect.bottom(), 600.0);
+        assert!(is_displayed(&iframe, &query(&root, ".bg-black\\/30").unwrap()));
+        assert!(is_displayed(&iframe, &query(&root, "[aria-label=Close]").unwrap()));
+    }
+
+    #[wasm_bindgen_test]
+    async fn wide_viewport_shows_a_popover_above_the_composer() {
+        let iframe = viewport(1024, STYLES).await;
+        let root = picker_in(&iframe).await;
+
+        let composer = query(&root, ".h-96").unwrap().get_bounding_client_rect();
+        let rect = sheet(&root).get_bounding_client_rect();
+        assert_eq!(rect.width(), 320.0);
+        assert_eq!(rect.right(), composer.right());
+        assert_eq!(rect.bottom(), composer.bottom() - 48.0);
+        assert!(!is_displayed(&iframe, &query(&root, ".bg-black\\/30").unwrap()));
+        assert!(!is_displayed(&iframe, &query(&root, "[aria-label=Close]").unwrap()));
+    }
+
+    #[wasm_bindgen_test]
+    async fn backdrop_and_handle_close_the_sheet() {
+        let closed = Rc::new(Cell::new(0));
+        let onclose = {
+            let closed = closed.clone();
+            Callback::from(move |_| closed.set(closed.get() + 1))
+        };
+        let root = render::<EmojiPicker>(props(onclose)).await;
+
+        query(&root, ".bg-black\\/30").unwrap().click();
+        query(&root, "[aria-label=Close]").unwrap().click();
+        settle().await;
+        assert_eq!(closed.get(), 2);
+    }
+}
@@ -0,0 +1,71 @@
+use yew::prelude::*;
+
+use crate::services::file_transfer::{format_size, Direction, TransferState, TransferView};
+
+#[derive(Properties, PartialEq)]
+pub struct FileCardProps {
+    pub transfer: TransferView,
+    pub oncancel: Callback<u64>,
+    #[prop_or_default]
+    pub dark: bool,
+}
+
+#[function_component(FileCard)]
+pub fn file_card(props: &FileCardProps) -> Html {
+    let transfer = &props.transfer;
+    let offer = &transfer.offer;
+    let percent = (transfer.progress() * 100.0).round();
+
+    let status = match (&transfer.state, transfer.direction) {
+        (TransferState::InProgress, Direction::Outgoing) => format!("Sending… {}%", percent),
+        (TransferState::InProgress, Direction::Incoming) => format!("Receiving… {}%", percent),
+        (TransferState::Paused, _) => format!("Paused at {}%, waiting for connection", percent),
+        (TransferState::Complete, Direction::Outgoing) => "Sent".to_string(),
+        (TransferState::Complete, Direction::Incoming) => "Verified".to_string(),
+        (TransferState::Cancelled, _) => "Cancelled".to_string(),
+        (TransferState::Failed(reason), _) => reason.clone(),
+    };
+    let pending = matches!(transfer.state, TransferState::InProgress | TransferState::Paused);
+
+    let oncancel = {
+        let oncancel = props.oncancel.clone();
+        let id = offer.id;
+        Callback::from(move |_| oncancel.emit(id))
+    };
+
+    html! {
+        <div class={classes!(
+            "w-64", "rounded-lg", "p-3",
+            if props.dark { "bg-white/10" } else { "bg-gray-50 border border-gray-200" }
+        )}>
+            <div class="flex items-center space-x-3">
+                <svg xmlns="http://www.w3.org/2000/svg" class="h-8 w-8 flex-shrink-0" fill="none" viewBox="0 0 24 24" stroke="currentColor">
+                    <path stroke-linecap="round" stroke-linejoin="round" stroke-width="1.5" d="M9 12h6m-6 4h6m2 5H7a2 2 0 01-2-2V5a2 2 0 012-2h5.586a1 1 0 01.707.293l5.414 5.414a1 1 0 01.293.707V19a2 2 0 01-2 2z" />
+                </svg>
+                <div class="min-w-0">
+                    <div class="font-medium truncate" title={offer.name.clone()}>{offer.name.clone()}</div>
+                    <div class="text-xs opacity-75">{format!("{} · {}", format_size(offer.size), offer.mime)}</div>
+                </div>
+            </div>
+            if pending {
+                <div class="mt-2 h-1.5 w-full bg-black/10 rounded-full overflow-hidden">
+                    <div class="h-full bg-purple-400 transition-all" style={format!("width: {}%;", percent)}></div>
+                </div>
+            }
+            <div class="mt-2 flex justify-between items-center text-xs">
+                <span class="opacity-75">{status}</span>
+                {
+                    match transfer.url.clone() {
+                        _ if pending => html! {
+                            <button onclick={oncancel} class="underline hover:no-underline">{"Cancel"}</button>
+                        },
+                        Some(url) => html! {
+                            <a href={url} download={offer.name.clone()} class="font-semibold underline hover:no-underline">{"Download"}</a>
+                        },
+                        None => html! {},
+                    }
+                }
+            </div>
+        </div>
+    }
+}
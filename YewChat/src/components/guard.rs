@@ -0,0 +1,31 @@
+use yew::prelude::*;
+use yew_router::prelude::*;
+
+use crate::services::session::{self, RedirectTarget};
+use crate::services::store;
+use crate::Route;
+
+#[derive(Properties, PartialEq)]
+pub struct RequireUserProps {
+    pub children: Children,
+}
+
+/// Renders its children only once a username is set; otherwise remembers
+/// the requested location and redirects to the login page.
+#[function_component(RequireUser)]
+pub fn require_user(props: &RequireUserProps) -> Html {
+    let signed_in = store::use_slice(|s| s.user.is_signed_in());
+    let location = use_location();
+
+    if !signed_in {
+        if let Some(location) = location {
+            session::save_redirect(&RedirectTarget {
+                path: location.pathname(),
+                query: location.query().unwrap_or_default(),
+            });
+        }
+        return html! { <Redirect<Route> to={Route::Login}/> };
+    }
+
+    html! { <>{ for props.children.iter() }</> }
+}
@@ -0,0 +1,165 @@
+use gloo::events::EventListener;
+use wasm_bindgen::JsCast;
+use yew::prelude::*;
+
+use crate::services::media::{MediaItem, MediaKind};
+
+const MIN_ZOOM: f64 = 0.5;
+const MAX_ZOOM: f64 = 4.0;
+const ZOOM_STEP: f64 = 0.25;
+
+#[derive(Properties, PartialEq)]
+pub struct LightboxProps {
+    pub items: Vec<MediaItem>,
+    pub index: usize,
+    pub onnavigate: Callback<usize>,
+    pub onclose: Callback<()>,
+}
+
+#[function_component(Lightbox)]
+pub fn lightbox(props: &LightboxProps) -> Html {
+    let zoom = use_state(|| 1.0_f64);
+    let count = props.items.len();
+    let index = props.index.min(count.saturating_sub(1));
+
+    {
+        // Reset the zoom whenever a different item is shown.
+        let zoom = zoom.clone();
+        use_effect_with_deps(
+            move |_| {
+                zoom.set(1.0);
+                || ()
+            },
+            index,
+        );
+    }
+
+    {
+        let zoom_level = *zoom;
+        let zoom = zoom.clone();
+        let onnavigate = props.onnavigate.clone();
+        let onclose = props.onclose.clone();
+        use_effect_with_deps(
+            move |(index, zoom_level)| {
+                let (index, zoom_level) = (*index, *zoom_level);
+                let document = web_sys::window().and_then(|w| w.document());
+                let listener = document.map(|document| {
+                    EventListener::new(&document, "keydown", move |e| {
+                        let e = match e.dyn_ref::<KeyboardEvent>() {
+                            Some(e) => e,
+                            None => return,
+                        };
+                        match e.key().as_str() {
+                            "Escape" => onclose.emit(()),
+                            "ArrowLeft" if count > 1 => onnavigate.emit((index + count - 1) % count),
+                            "ArrowRight" if count > 1 => onnavigate.emit((index + 1) % count),
+                            "+" | "=" => zoom.set((zoom_level + ZOOM_STEP).min(MAX_ZOOM)),
+                            "-" => zoom.set((zoom_level - ZOOM_STEP).max(MIN_ZOOM)),
+                            "0" => zoom.set(1.0),
+                            _ => return,
+                        }
+                        e.prevent_default();
+                    })
+                });
+                move || drop(listener)
+            },
+            (index, zoom_level),
+        );
+    }
+
+    let item = match props.items.get(index) {
+        Some(item) => item.clone(),
+        None => return html! {},
+    };
+    let previous = (index + count - 1) % count;
+    let next = (index + 1) % count;
+
+    let close = {
+        let onclose = props.onclose.clone();
+        Callback::from(move |_| onclose.emit(()))
+    };
+    let go_previous = {
+        let onnavigate = props.onnavigate.clone();
+        Callback::from(move |e: MouseEvent| {
+            e.stop_propagation();
+            onnavigate.emit(previous)
+        })
+    };
+    let go_next = {
+        let onnavigate = props.onnavigate.clone();
+        Callback::from(move |e: MouseEvent| {
+            e.stop_propagation();
+            onnavigate.emit(next)
+        })
+    };
+    let zoom_in = {
+        let zoom = zoom.clone();
+        Callback::from(move |e: MouseEvent| {
+            e.stop_propagation();
+            zoom.set((*zoom + ZOOM_STEP).min(MAX_ZOOM))
+        })
+    };
+    let zoom_out = {
+        let zoom = zoom.clone();
+        Callback::from(move |e: MouseEvent| {
+            e.stop_propagation();
+            zoom.set((*zoom - ZOOM_STEP).max(MIN_ZOOM))
+        })
+    };
+    let toggle_zoom = {
+        let zoom = zoom.clone();
+        Callback::from(move |e: MouseEvent| {
+            e.stop_propagation();
+            zoom.set(if *zoom > 1.0 { 1.0 } else { 2.0 })
+        })
+    };
+    let stop = Callback::from(|e: MouseEvent| e.stop_propagation());
+
+    html! {
+        <div onclick={close.clone()} class="fixed inset-0 z-50 bg-black/90 flex flex-col">
+            <div class="flex justify-between items-center p-4 text-white">
+                <div class="text-sm">
+                    <span class="font-semibold">{item.from.clone()}</span>
+                    <span class="ml-2 text-gray-400">{format!("{} / {}", index + 1, count)}</span>
+                </div>
+                <div class="flex items-center space-x-2" onclick={stop.clone()}>
+                    if item.kind == MediaKind::Image {
+                        <button onclick={zoom_out} class="bg-white/20 hover:bg-white/30 rounded-lg px-3 py-1" title="Zoom out (-)">{"−"}</button>
+                        <span class="text-sm w-12 text-center">{format!("{:.0}%", *zoom * 100.0)}</span>
+                        <button onclick={zoom_in} class="bg-white/20 hover:bg-white/30 rounded-lg px-3 py-1" title="Zoom in (+)">{"+"}</button>
+                    }
+                    <button onclick={close} class="bg-white/20 hover:bg-white/30 rounded-lg px-3 py-1" title="Close (Esc)">{"✕"}</button>
+                </div>
+            </div>
+            <div class="flex-1 flex items-center justify-between overflow-hidden">
+                if count > 1 {
+                    <button onclick={go_previous} class="text-white text-4xl px-4 hover:text-purple-300" title="Previous (←)">{"‹"}</button>
+                } else {
+                    <div></div>
+                }
+                <div class="flex-1 h-full flex items-center justify-center overflow-auto">
+                    {
+                        match item.kind {
+                            MediaKind::Image => html! {
+                                <img
+                                    onclick={toggle_zoom}
+                                    src={item.url.clone()}
+                                    class="max-h-full max-w-full transition-transform cursor-zoom-in"
+                                    style={format!("transform: scale({});", *zoom)}
+                                />
+                            },
+                            MediaKind::Video => html! {
+                                <video onclick={stop} src={item.url.clone()} controls=true autoplay=true class="max-h-full max-w-full"/>
+                            },
+                        }
+                    }
+                </div>
+                if count > 1 {
+                    <button onclick={go_next} class="text-white text-4xl px-4 hover:text-purple-300" title="Next (→)">{"›"}</button>
+                } else {
+                    <div></div>
+                }
+            </div>
+        </div>
+    }
+}
@@ -1,15 +1,95 @@
+use wasm_bindgen_futures::spawn_local;
 use web_sys::HtmlInputElement;
 use yew::functional::*;
 use yew::prelude::*;
 use yew_router::prelude::*;
 
+use crate::services::auth::{self, AuthToken};
+use crate::services::session::{self, RedirectTarget, RememberedUser};
+use crate::services::store::{self, Action};
+use crate::services::username;
 use crate::Route;
-use crate::User;
+
+/// How the user proves who they are.
+#[derive(Clone, Copy, PartialEq)]
+enum LoginMode {
+    /// Anonymous; any free nickname that isn't an account's.
+    Guest,
+    Password,
+    /// A code delivered out of band by the auth service.
+    Code,
+}
+
+impl LoginMode {
+    const ALL: [LoginMode; 3] = [LoginMode::Guest, LoginMode::Password, LoginMode::Code];
+
+    fn label(&self) -> &'static str {
+        match self {
+            LoginMode::Guest => "Guest",
+            LoginMode::Password => "Password",
+            LoginMode::Code => "One-time code",
+        }
+    }
+}
 
 #[function_component(Login)]
 pub fn login() -> Html {
-    let username = use_state(|| String::new());
-    let user = use_context::<User>().expect("No context found.");
+    // A nickname the server refused, e.g. because someone online has it.
+    let rejection = use_state(session::take_rejection);
+    let username = {
+        let rejection = rejection.clone();
+        use_state(move || (*rejection).as_ref().map(|r| r.nick.clone()).unwrap_or_default())
+    };
+    let remember_me = use_state(|| false);
+    let mode = use_state(|| LoginMode::Guest);
+    let password = use_state(String::new);
+    let code = use_state(String::new);
+    let code_sent = use_state(|| false);
+    let busy = use_state(|| false);
+    let auth_error = use_state(|| None::<String>);
+    let history = use_history().expect("history to be available");
+
+    // A remembered user goes straight back to where they left off.
+    {
+        let history = history.clone();
+        let rejected = rejection.is_some();
+        use_effect_with_deps(
+            move |_| {
+                if let Some(identity) = session::remembered().filter(|_| !rejected) {
+                    store::dispatch(Action::SetUsername(identity.username));
+                    enter_chat(&history, session::take_redirect().or(identity.last_location));
+                }
+                || ()
+            },
+            (),
+        );
+    }
+
+    let validation = username::validate(&username);
+    let rejected = (*rejection)
+        .as_ref()
+        .filter(|r| Ok(&r.nick) == validation.as_ref());
+    let error = match (&validation, rejected) {
+        (_, Some(r)) => r.error.clone(),
+        // Don't nag before anything has been typed.
+        (Err(_), None) if username.is_empty() => None,
+        (Err(e), None) => Some(e.clone()),
+        (Ok(_), None) => None,
+    }
+    .or_else(|| (*auth_error).clone());
+    let can_submit = validation.is_ok()
+        && !*busy
+        && match *mode {
+            LoginMode::Guest => rejected.is_none(),
+            LoginMode::Password => !password.is_empty(),
+            LoginMode::Code => !*code_sent || !code.trim().is_empty(),
+        };
+    let submit_label = match *mode {
+        LoginMode::Guest => "Start Chatting!",
+        LoginMode::Code if !*code_sent => "Send me a code",
+        _ if *busy => "Signing in…",
+        _ => "Sign in",
+    };
 
     let oninput = {
         let current_username = username.clone();
@@ -20,10 +100,79 @@ pub fn login() -> Html {
         })
     };
 
-    let onclick = {
+    let select_mode = |target: LoginMode| {
+        let mode = mode.clone();
+        let auth_error = auth_error.clone();
+        Callback::from(move |_| {
+            mode.set(target);
+            auth_error.set(None);
+        })
+    };
+
+    let input_setter = |state: &UseStateHandle<String>| {
+        let state = state.clone();
+        let auth_error = auth_error.clone();
+        Callback::from(move |e: InputEvent| {
+            let input: HtmlInputElement = e.target_unchecked_into();
+            state.set(input.value());
+            auth_error.set(None);
+        })
+    };
+
+    let toggle_remember = {
+        let remember_me = remember_me.clone();
+        Callback::from(move |_| remember_me.set(!*remember_me))
+    };
+
+    let onsubmit = {
         let username = username.clone();
-        let user = user.clone();
-        Callback::from(move |_| *user.username.borrow_mut() = (*username).clone())
+        let remember_me = remember_me.clone();
+        let mode = mode.clone();
+        let password = password.clone();
+        let code = code.clone();
+        let code_sent = code_sent.clone();
+        let busy = busy.clone();
+        let auth_error = auth_error.clone();
+        Callback::from(move |e: FocusEvent| {
+            e.prevent_default();
+            let name = match username::validate(&username) {
+                Ok(name) => name,
+                Err(_) => return,
+            };
+            let remember = *remember_me;
+
+            if *mode == LoginMode::Guest {
+                sign_in(&history, name, remember, None);
+                return;
+            }
+
+            busy.set(true);
+            auth_error.set(None);
+            let (mode, password, code) = (*mode, (*password).clone(), code.trim().to_string());
+            let history = history.clone();
+            let (code_sent, busy, auth_error) = (code_sent.clone(), busy.clone(), auth_error.clone());
+            spawn_local(async move {
+                let result = match mode {
+                    LoginMode::Code if !*code_sent => match auth::request_code(&name).await {
+                        Ok(()) => {
+                            code_sent.set(true);
+                            busy.set(false);
+                            return;
+                        }
+                        Err(e) => Err(e),
+                    },
+                    LoginMode::Code => auth::sign_in_with_code(&name, &code).await,
+                    _ => auth::sign_in_with_password(&name, &password).await,
+                };
+                match result {
+                    Ok(token) => sign_in(&history, name, remember, Some(token)),
+                    Err(e) => {
+                        auth_error.set(Some(e));
+                        busy.set(false);
+                    }
+                }
+            });
+        })
     };
 
     html! {
@@ -48,7 +197,33 @@ pub fn login() -> Html {
                         </svg>
                     </div>
                     
-                    <form class="flex flex-col">
+                    <div class="mb-4 flex rounded-lg bg-white/10 p-1 text-sm">
+                        {
+                            for LoginMode::ALL.iter().map(|m| html! {
+                                <button
+                                    type="button"
+                                    onclick={select_mode(*m)}
+                                    class={classes!(
+                                        "flex-1", "rounded-md", "py-2", "transition-colors",
+                                        if *mode == *m { "bg-white/30 text-white font-semibold" } else { "text-purple-100 hover:bg-white/10" }
+                                    )}
+                                >
+                                    {m.label()}
+                                </button>
+                            })
+                        }
+                    </div>
+                    <p class="mb-4 text-xs text-purple-200">
+                        {
+                            match *mode {
+                                LoginMode::Guest => "Join anonymously. Names that belong to an account are reserved for it.",
+                                LoginMode::Password => "Sign in to your account to keep your name.",
+                                LoginMode::Code => "We'll send a one-time code to your account.",
+                            }
+                        }
+                    </p>
+
+                    <form class="flex flex-col" {onsubmit}>
                         <div class="relative mb-4">
                             <div class="absolute inset-y-0 left-0 flex items-center pl-3 pointer-events-none">
                                 <svg xmlns="http://www.w3.org/2000/svg" class="h-5 w-5 text-purple-300" viewBox="0 0 20 20" fill="currentColor">
@@ -57,22 +232,111 @@ pub fn login() -> Html {
                             </div>
                             <input 
                                 {oninput} 
+                                value={(*username).clone()}
+                                maxlength="64"
                                 class="bg-white/20 text-white placeholder-purple-200 rounded-lg p-4 pl-10 w-full border border-purple-400/30 focus:border-purple-400 focus:outline-none focus:ring-2 focus:ring-purple-400/50 transition-all" 
                                 placeholder="Enter your username" 
                             />
                         </div>
-                        <Link<Route> to={Route::Chat} classes="w-full"> 
-                            <button 
-                                {onclick} 
-                                disabled={username.len()<1} 
-                                class="w-full px-8 rounded-lg bg-gradient-to-r from-purple-600 to-indigo-600 hover:from-purple-700 hover:to-indigo-700 text-white font-bold p-4 uppercase shadow-lg hover:shadow-xl transition-all disabled:opacity-50 disabled:cursor-not-allowed"
-                            >
-                                {"Start Chatting!"}
-                            </button>
-                        </Link<Route>>
+                        if *mode == LoginMode::Password {
+                            <input
+                                oninput={input_setter(&password)}
+                                value={(*password).clone()}
+                                type="password"
+                                autocomplete="current-password"
+                                class="mb-4 bg-white/20 text-white placeholder-purple-200 rounded-lg p-4 w-full border border-purple-400/30 focus:border-purple-400 focus:outline-none focus:ring-2 focus:ring-purple-400/50 transition-all"
+                                placeholder="Password"
+                            />
+                        }
+                        if *mode == LoginMode::Code && *code_sent {
+                            <input
+                                oninput={input_setter(&code)}
+                                value={(*code).clone()}
+                                inputmode="numeric"
+                                autocomplete="one-time-code"
+                                class="mb-4 bg-white/20 text-white placeholder-purple-200 rounded-lg p-4 w-full border border-purple-400/30 focus:border-purple-400 focus:outline-none focus:ring-2 focus:ring-purple-400/50 transition-all tracking-widest"
+                                placeholder="6-digit code"
+                            />
+                        }
+                        if let Some(error) = error {
+                            <p class="-mt-2 mb-2 text-sm text-red-200">{error}</p>
+                        }
+                        if let Some(r) = rejected.filter(|r| !r.suggestions.is_empty()) {
+                            <div class="mb-4 flex flex-wrap items-center gap-2 text-sm text-purple-100">
+                                {"Try:"}
+                                {
+                                    for r.suggestions.iter().map(|suggestion| {
+                                        let username = username.clone();
+                                        let pick = suggestion.clone();
+                                        html! {
+                                            <button
+                                                type="button"
+                                                onclick={Callback::from(move |_| username.set(pick.clone()))}
+                                                class="bg-white/20 hover:bg-white/30 rounded px-2 py-1 transition-colors"
+                                            >
+                                                {suggestion.clone()}
+                                            </button>
+                                        }
+                                    })
+                                }
+                            </div>
+                        }
+                        <label class="mb-4 flex items-center text-sm text-purple-100 cursor-pointer select-none">
+                            <input
+                                type="checkbox"
+                                class="mr-2 accent-purple-500"
+                                checked={*remember_me}
+                                onchange={toggle_remember}
+                            />
+                            {"Remember me on this device"}
+                        </label>
+                        <button 
+                            type="submit"
+                            disabled={!can_submit} 
+                            class="w-full px-8 rounded-lg bg-gradient-to-r from-purple-600 to-indigo-600 hover:from-purple-700 hover:to-indigo-700 text-white font-bold p-4 uppercase shadow-lg hover:shadow-xl transition-all disabled:opacity-50 disabled:cursor-not-allowed"
+                        >
+                            {submit_label}
+                        </button>
                     </form>
                 </div>
             </div>
         </div>
     }
-}
\ No newline at end of file
+}
+
+/// Stores the identity, signs in with `token` or as a guest, and enters the
+/// chat, restoring the deep link that bounced the user here.
+fn sign_in(history: &AnyHistory, name: String, remember: bool, token: Option<AuthToken>) {
+    if remember {
+        session::remember(&RememberedUser {
+            username: name.clone(),
+            resume_token: None,
+            last_location: None,
+            auth: None,
+        });
+    }
+    match token {
+        Some(token) => session::save_auth(&token),
+        None => session::clear_auth(),
+    }
+    store::dispatch(Action::SetUsername(name));
+    enter_chat(history, session::take_redirect());
+}
+
+/// Opens `target` if it is a valid page other than the login page itself,
+/// falling back to the chat.
+fn enter_chat(history: &AnyHistory, target: Option<RedirectTarget>) {
+    let target = target
+        .and_then(|t| Route::recognize(&t.path).map(|route| (route, t.query)))
+        .filter(|(route, _)| *route != Route::Login);
+    match target {
+        Some((route, query)) if !query.is_empty() => {
+            if let Err(e) = history.push_with_query(route, query) {
+                log::error!("failed to restore deep link: {:?}", e);
+                history.push(Route::Chat);
+            }
+        }
+        Some((route, _)) => history.push(route),
+        None => history.push(Route::Chat),
+    }
+}
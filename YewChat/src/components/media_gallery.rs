@@ -0,0 +1,51 @@
+use yew::prelude::*;
+
+use crate::services::media::{MediaItem, MediaKind};
+
+#[derive(Properties, PartialEq)]
+pub struct MediaGalleryProps {
+    pub items: Vec<MediaItem>,
+    pub onselect: Callback<usize>,
+}
+
+#[function_component(MediaGallery)]
+pub fn media_gallery(props: &MediaGalleryProps) -> Html {
+    if props.items.is_empty() {
+        return html! {
+            <div class="p-4 text-center text-gray-500 italic">
+                {"No media shared yet"}
+            </div>
+        };
+    }
+
+    html! {
+        <div class="grid grid-cols-3 gap-1 p-2">
+            {
+                props.items.iter().enumerate().map(|(i, item)| {
+                    let onclick = props.onselect.reform(move |_| i);
+                    html! {
+                        <button
+                            {onclick}
+                            class="relative aspect-square overflow-hidden rounded bg-gray-200 hover:opacity-80 transition-opacity"
+                            title={format!("Shared by {}", item.from)}
+                        >
+                            {
+                                match item.kind {
+                                    MediaKind::Image => html! {
+                                        <img class="w-full h-full object-cover" src={item.url.clone()} loading="lazy"/>
+                                    },
+                                    MediaKind::Video => html! {
+                                        <>
+                                            <video class="w-full h-full object-cover" src={item.url.clone()} preload="metadata"/>
+                                            <div class="absolute inset-0 flex items-center justify-center text-white text-2xl bg-black/30">{"▶"}</div>
+                                        </>
+                                    },
+                                }
+                            }
+                        </button>
+                    }
+                }).collect::<Html>()
+            }
+        </div>
+    }
+}
@@ -1,2 +1,13 @@
 pub mod chat;
-pub mod login;
\ No newline at end of file
+pub mod drawer;
+pub mod emoji_picker;
+pub mod file_card;
+pub mod guard;
+pub mod lightbox;
+pub mod login;
+pub mod media_gallery;
+pub mod profile;
+pub mod profile_card;
+pub mod settings;
+pub mod status_menu;
+pub mod user_list;
\ No newline at end of file
@@ -0,0 +1,95 @@
+use yew::prelude::*;
+use yew_router::prelude::*;
+
+use crate::services::avatar::avatar_url;
+use crate::services::history::MessageHistory;
+use crate::services::last_seen::{self, LastSeen};
+use crate::services::store;
+use crate::Route;
+
+/// How many of the user's messages the page lists.
+const RECENT_MESSAGES: usize = 50;
+
+#[derive(Properties, PartialEq)]
+pub struct ProfilePageProps {
+    pub name: String,
+}
+
+/// Everything this device remembers about one user, including their most
+/// recent messages from the local history.
+#[function_component(ProfilePage)]
+pub fn profile_page(props: &ProfilePageProps) -> Html {
+    let avatar_style = store::use_slice(|s| s.settings.avatar_style);
+    let history = use_state(MessageHistory::load);
+    let seen = use_state(LastSeen::load);
+    let now = js_sys::Date::now();
+
+    let messages: Vec<_> = history.by(&props.name).collect();
+    let recent = &messages[messages.len().saturating_sub(RECENT_MESSAGES)..];
+    let seen = seen.get(&props.name);
+
+    html! {
+        <div class="flex flex-col w-full h-screen bg-gray-100">
+            <header class="bg-gradient-to-r from-purple-800 to-indigo-800 text-white shadow-lg">
+                <div class="container mx-auto px-4 py-3 flex items-center space-x-4">
+                    <Link<Route> to={Route::Chat} classes="text-purple-200 hover:text-white text-sm">
+                        {"← Back to chat"}
+                    </Link<Route>>
+                    <h1 class="text-2xl font-bold">{props.name.clone()}</h1>
+                </div>
+            </header>
+            <div class="flex-1 overflow-y-auto">
+                <div class="container mx-auto max-w-2xl p-4 space-y-4">
+                    <div class="flex items-center space-x-4 bg-white rounded-xl shadow p-4">
+                        <img class="w-24 h-24 rounded-full" src={avatar_url(&props.name, avatar_style)} alt="avatar"/>
+                        <dl class="grid grid-cols-2 gap-x-4 gap-y-1 text-sm">
+                            if let Some(seen) = seen {
+                                <dt class="text-gray-500">{"First seen"}</dt>
+                                <dd>{last_seen::ago(seen.first_seen, now)}</dd>
+                                <dt class="text-gray-500">{"Last seen"}</dt>
+                                <dd>{last_seen::ago(seen.last_seen, now)}</dd>
+                            } else {
+                                <dt class="text-gray-500 col-span-2">{"Not seen on this device yet"}</dt>
+                            }
+                            <dt class="text-gray-500">{"Messages here"}</dt>
+                            <dd>{messages.len()}</dd>
+                        </dl>
+                    </div>
+                    <div class="bg-white rounded-xl shadow">
+                        <h2 class="px-4 py-3 border-b text-sm font-semibold text-gray-700">{"Recent messages"}</h2>
+                        if recent.is_empty() {
+                            <div class="p-4 text-center text-gray-500 italic">{"No messages in this device's history"}</div>
+                        } else {
+                            <ul class="divide-y">
+                                {
+                                    for recent.iter().rev().map(|m| html! {
+                                        <li class="px-4 py-2">
+                                            <div class="flex justify-between text-xs text-gray-400">
+                                                <span>
+                                                    {
+                                                        match &m.to {
+                                                            Some(to) => format!("to {} (private)", to),
+                                                            None => "to everyone".to_string(),
+                                                        }
+                                                    }
+                                                </span>
+                                                <span>{last_seen::ago(m.time, now)}</span>
+                                            </div>
+                                            <p class="text-sm text-gray-800 whitespace-pre-wrap break-words">
+                                                if m.emote {
+                                                    {format!("* {} {}", m.from, m.message)}
+                                                } else {
+                                                    {m.message.clone()}
+                                                }
+                                            </p>
+                                        </li>
+                                    })
+                                }
+                            </ul>
+                        }
+                    </div>
+                </div>
+            </div>
+        </div>
+    }
+}
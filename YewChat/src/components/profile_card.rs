@@ -0,0 +1,105 @@
+use yew::prelude::*;
+use yew_router::prelude::*;
+
+use crate::services::avatar::{avatar_url, AvatarStyle};
+use crate::services::last_seen::{self, SeenUser};
+use crate::services::presence::Presence;
+use crate::Route;
+
+#[derive(Properties, PartialEq)]
+pub struct ProfileCardProps {
+    pub name: String,
+    /// `None` while the user is offline.
+    pub presence: Option<Presence>,
+    pub seen: Option<SeenUser>,
+    /// Messages by this user in the local history.
+    pub message_count: usize,
+    pub ignored: bool,
+    /// Whether this is the signed-in user's own card.
+    pub is_self: bool,
+    pub avatar_style: AvatarStyle,
+    pub now: f64,
+    pub ondirect: Callback<String>,
+    pub onmention: Callback<String>,
+    pub onignore: Callback<String>,
+    pub onclose: Callback<()>,
+}
+
+/// Popover with what this device knows about a user, and quick actions.
+#[function_component(ProfileCard)]
+pub fn profile_card(props: &ProfileCardProps) -> Html {
+    let onclose = {
+        let onclose = props.onclose.clone();
+        Callback::from(move |_| onclose.emit(()))
+    };
+    let action = |callback: &Callback<String>| {
+        let callback = callback.clone();
+        let name = props.name.clone();
+        Callback::from(move |_: MouseEvent| callback.emit(name.clone()))
+    };
+
+    let (status_color, status_label) = match &props.presence {
+        Some(presence) => (
+            presence.status.color(),
+            match &presence.text {
+                Some(text) => format!("{} · {}", presence.status.label(), text),
+                None => presence.status.label().to_string(),
+            },
+        ),
+        None => ("bg-gray-300", "Offline".to_string()),
+    };
+
+    html! {
+        <div class="fixed inset-0 z-30 flex items-center justify-center bg-black/20" onclick={onclose.clone()}>
+            <div
+                class="w-72 bg-white text-gray-800 rounded-xl shadow-2xl border border-gray-200"
+                onclick={Callback::from(|e: MouseEvent| e.stop_propagation())}
+            >
+                <div class="flex justify-end px-2 pt-2">
+                    <button onclick={onclose} class="text-gray-400 hover:text-gray-600">{"✕"}</button>
+                </div>
+                <div class="flex flex-col items-center px-4 pb-4">
+                    <div class="relative">
+                        <img class="w-20 h-20 rounded-full" src={avatar_url(&props.name, props.avatar_style)} alt="avatar"/>
+                        <span class={classes!(
+                            "absolute", "bottom-1", "right-1", "w-4", "h-4", "rounded-full", "border-2", "border-white",
+                            status_color
+                        )}></span>
+                    </div>
+                    <h2 class="mt-2 text-lg font-semibold">{props.name.clone()}</h2>
+                    <div class="text-xs text-gray-500 text-center">{status_label}</div>
+                </div>
+                <dl class="px-4 pb-3 grid grid-cols-2 gap-y-1 text-xs">
+                    if let Some(seen) = &props.seen {
+                        <dt class="text-gray-500">{"First seen"}</dt>
+                        <dd class="text-right">{last_seen::ago(seen.first_seen, props.now)}</dd>
+                        if props.presence.is_none() {
+                            <dt class="text-gray-500">{"Last seen"}</dt>
+                            <dd class="text-right">{last_seen::ago(seen.last_seen, props.now)}</dd>
+                        }
+                    }
+                    <dt class="text-gray-500">{"Messages here"}</dt>
+                    <dd class="text-right">{props.message_count}</dd>
+                </dl>
+                if !props.is_self {
+                    <div class="flex border-t text-sm">
+                        <button onclick={action(&props.ondirect)} class="flex-1 py-2 text-purple-600 hover:bg-purple-50">
+                            {"Message"}
+                        </button>
+                        <button onclick={action(&props.onmention)} class="flex-1 py-2 text-purple-600 hover:bg-purple-50">
+                            {"Mention"}
+                        </button>
+                        <button onclick={action(&props.onignore)} class="flex-1 py-2 text-red-500 hover:bg-red-50">
+                            { if props.ignored { "Unignore" } else { "Ignore" } }
+                        </button>
+                    </div>
+                }
+                <div class="border-t px-4 py-2 text-center">
+                    <Link<Route> to={Route::Profile { name: props.name.clone() }} classes="text-xs text-purple-600 hover:underline">
+                        {"View full profile"}
+                    </Link<Route>>
+                </div>
+            </div>
+        </div>
+    }
+}
@@ -0,0 +1,534 @@
+use gloo::file::File;
+use web_sys::{HtmlInputElement, NotificationPermission};
+use yew::prelude::*;
+
+use crate::services::avatar::{avatar_url, AvatarStyle};
+use crate::services::custom_emoji::EmojiPack;
+use crate::services::dnd::{self, DAY_LABELS};
+use crate::services::notifications::NotifyMode;
+use crate::services::settings::Settings;
+use crate::services::sounds::SoundEvent;
+use crate::services::username;
+
+/// Idle minutes offered for auto-away; 0 turns it off.
+const AUTO_AWAY_CHOICES: [u32; 5] = [0, 2, 5, 10, 30];
+
+#[derive(Properties, PartialEq)]
+pub struct SettingsPanelProps {
+    pub settings: Settings,
+    pub username: String,
+    pub onchange: Callback<Settings>,
+    pub onclose: Callback<()>,
+    #[prop_or_default]
+    pub packs: Vec<EmojiPack>,
+    pub onimportpack: Callback<File>,
+    pub onsharepack: Callback<String>,
+    pub onremovepack: Callback<String>,
+    /// Called with the validated new nickname.
+    pub onrename: Callback<String>,
+    /// Why the server refused the last rename, if it did.
+    #[prop_or_default]
+    pub rename_error: Option<String>,
+    /// The open room, whose notification mode the panel edits.
+    #[prop_or_default]
+    pub room: Option<String>,
+    pub notification_permission: NotificationPermission,
+    pub onrequestnotifications: Callback<()>,
+    /// Plays a sound cue at the given volume so it can be tried out.
+    pub onpreviewsound: Callback<(SoundEvent, f32)>,
+}
+
+#[function_component(SettingsPanel)]
+pub fn settings_panel(props: &SettingsPanelProps) -> Html {
+    let onclose = {
+        let onclose = props.onclose.clone();
+        Callback::from(move |_| onclose.emit(()))
+    };
+
+    let nickname = use_state(|| props.username.clone());
+    {
+        // Pick up renames that land while the panel is open, including ours.
+        let nickname = nickname.clone();
+        use_effect_with_deps(
+            move |username: &String| {
+                nickname.set(username.clone());
+                || ()
+            },
+            props.username.clone(),
+        );
+    }
+    let validation = username::validate(&nickname);
+    let unchanged = validation.as_deref() == Ok(props.username.as_str());
+    let oninput = {
+        let nickname = nickname.clone();
+        Callback::from(move |e: InputEvent| {
+            let input: HtmlInputElement = e.target_unchecked_into();
+            nickname.set(input.value());
+        })
+    };
+    let onrename = {
+        let onrename = props.onrename.clone();
+        let nickname = nickname.clone();
+        Callback::from(move |e: FocusEvent| {
+            e.prevent_default();
+            if let Ok(name) = username::validate(&nickname) {
+                onrename.emit(name);
+            }
+        })
+    };
+    let nickname_error = match &validation {
+        Err(e) => Some(e.clone()),
+        Ok(_) if unchanged => None,
+        Ok(_) => props.rename_error.clone(),
+    };
+
+    let toggle_join_leave = {
+        let settings = props.settings.clone();
+        let onchange = props.onchange.clone();
+        Callback::from(move |_| {
+            let mut settings = settings.clone();
+            settings.show_join_leave = !settings.show_join_leave;
+            onchange.emit(settings);
+        })
+    };
+
+    let notify_mode = props.settings.notifications.mode_for(props.room.as_deref());
+    let onkeywords = {
+        let settings = props.settings.clone();
+        let onchange = props.onchange.clone();
+        Callback::from(move |e: Event| {
+            let input: HtmlInputElement = e.target_unchecked_into();
+            let mut settings = settings.clone();
+            settings.notifications.keywords = input
+                .value()
+                .split(',')
+                .map(|keyword| keyword.trim().to_string())
+                .filter(|keyword| !keyword.is_empty())
+                .collect();
+            onchange.emit(settings);
+        })
+    };
+    let onrequestnotifications = {
+        let onrequestnotifications = props.onrequestnotifications.clone();
+        Callback::from(move |_| onrequestnotifications.emit(()))
+    };
+
+    let toggle_sounds = {
+        let settings = props.settings.clone();
+        let onchange = props.onchange.clone();
+        Callback::from(move |_| {
+            let mut settings = settings.clone();
+            settings.sounds.enabled = !settings.sounds.enabled;
+            onchange.emit(settings);
+        })
+    };
+
+    // Applies `edit` to a copy of the settings and reports it.
+    let edit_settings = |edit: fn(&mut Settings, &str)| {
+        let settings = props.settings.clone();
+        let onchange = props.onchange.clone();
+        Callback::from(move |e: Event| {
+            let input: HtmlInputElement = e.target_unchecked_into();
+            let mut settings = settings.clone();
+            edit(&mut settings, &input.value());
+            onchange.emit(settings);
+        })
+    };
+    let schedule = &props.settings.dnd.schedule;
+
+    let onimport = {
+        let onimportpack = props.onimportpack.clone();
+        Callback::from(move |e: Event| {
+            let input: HtmlInputElement = e.target_unchecked_into();
+            if let Some(file) = input.files().and_then(|files| files.get(0)) {
+                onimportpack.emit(File::from(file));
+            }
+            input.set_value("");
+        })
+    };
+
+    html! {
+        <div class="absolute right-4 top-16 z-20 w-80 bg-white text-gray-800 rounded-xl shadow-2xl border border-gray-200">
+            <div class="flex justify-between items-center p-4 border-b">
+                <h2 class="text-lg font-semibold">{"Settings"}</h2>
+                <button onclick={onclose} class="text-gray-400 hover:text-gray-600">{"✕"}</button>
+            </div>
+            <form class="p-4 border-b" onsubmit={onrename}>
+                <div class="text-sm font-medium text-gray-700 mb-2">{"Nickname"}</div>
+                <div class="flex gap-2">
+                    <input
+                        {oninput}
+                        value={(*nickname).clone()}
+                        maxlength="64"
+                        class="flex-1 min-w-0 px-2 py-1 text-sm bg-gray-100 rounded border border-gray-200 focus:outline-none focus:border-purple-400"
+                    />
+                    <button
+                        type="submit"
+                        disabled={validation.is_err() || unchanged}
+                        class="px-3 py-1 text-sm rounded bg-purple-600 text-white hover:bg-purple-700 disabled:opacity-50"
+                    >
+                        {"Change"}
+                    </button>
+                </div>
+                if let Some(error) = nickname_error {
+                    <div class="mt-1 text-xs text-red-600">{error}</div>
+                }
+            </form>
+            <div class="p-4">
+                <div class="text-sm font-medium text-gray-700 mb-2">{"Avatar style"}</div>
+                <div class="grid grid-cols-3 gap-2">
+                    {
+                        AvatarStyle::ALL.iter().map(|style| {
+                            let style = *style;
+                            let selected = props.settings.avatar_style == style;
+                            let onclick = {
+                                let settings = props.settings.clone();
+                                let onchange = props.onchange.clone();
+                                Callback::from(move |_| {
+                                    let mut settings = settings.clone();
+                                    settings.avatar_style = style;
+                                    onchange.emit(settings);
+                                })
+                            };
+                            html! {
+                                <button
+                                    {onclick}
+                                    class={classes!(
+                                        "flex", "flex-col", "items-center", "p-2", "rounded-lg", "border", "transition-colors",
+                                        if selected { "border-purple-500 bg-purple-50" } else { "border-gray-200 hover:bg-gray-50" }
+                                    )}
+                                >
+                                    <img class="w-10 h-10 rounded-full mb-1" src={avatar_url(&props.username, style)} alt={style.label()}/>
+                                    <span class="text-xs">{style.label()}</span>
+                                </button>
+                            }
+                        }).collect::<Html>()
+                    }
+                </div>
+            </div>
+            <div class="p-4 border-t">
+                <label class="flex items-center text-sm text-gray-700 cursor-pointer select-none">
+                    <input
+                        type="checkbox"
+                        class="mr-2 accent-purple-600"
+                        checked={props.settings.show_join_leave}
+                        onchange={toggle_join_leave}
+                    />
+                    {"Show when people join or leave"}
+                </label>
+            </div>
+            <div class="p-4 border-t">
+                <div class="text-sm font-medium text-gray-700 mb-2">{"Show me as away after"}</div>
+                <div class="flex gap-1">
+                    {
+                        for AUTO_AWAY_CHOICES.iter().map(|minutes| {
+                            let minutes = *minutes;
+                            let selected = props.settings.auto_away_minutes == minutes;
+                            let onclick = {
+                                let settings = props.settings.clone();
+                                let onchange = props.onchange.clone();
+                                Callback::from(move |_| {
+                                    let mut settings = settings.clone();
+                                    settings.auto_away_minutes = minutes;
+                                    onchange.emit(settings);
+                                })
+                            };
+                            html! {
+                                <button
+                                    {onclick}
+                                    class={classes!(
+                                        "flex-1", "py-1", "text-xs", "rounded", "border", "transition-colors",
+                                        if selected { "border-purple-500 bg-purple-50" } else { "border-gray-200 hover:bg-gray-50" }
+                                    )}
+                                >
+                                    { if minutes == 0 { "Never".to_string() } else { format!("{} min", minutes) } }
+                                </button>
+                            }
+                        })
+                    }
+                </div>
+            </div>
+            <div class="p-4 border-t">
+                <div class="text-sm font-medium text-gray-700 mb-2">{"Desktop notifications"}</div>
+                {
+                    match props.notification_permission {
+                        NotificationPermission::Granted => html! {
+                            <>
+                                <div class="text-xs text-gray-500 mb-1">
+                                    {
+                                        match &props.room {
+                                            Some(room) => format!("Notify me in #{} about", room),
+                                            None => "Notify me about".to_string(),
+                                        }
+                                    }
+                                </div>
+                                <div class="flex gap-1 mb-3">
+                                    {
+                                        for NotifyMode::ALL.iter().map(|mode| {
+                                            let mode = *mode;
+                                            let onclick = {
+                                                let settings = props.settings.clone();
+                                                let onchange = props.onchange.clone();
+                                                let room = props.room.clone();
+                                                Callback::from(move |_| {
+                                                    let mut settings = settings.clone();
+                                                    settings.notifications.set_mode(room.as_deref(), mode);
+                                                    onchange.emit(settings);
+                                                })
+                                            };
+                                            html! {
+                                                <button
+                                                    {onclick}
+                                                    class={classes!(
+                                                        "flex-1", "py-1", "text-xs", "rounded", "border", "transition-colors",
+                                                        if notify_mode == mode { "border-purple-500 bg-purple-50" } else { "border-gray-200 hover:bg-gray-50" }
+                                                    )}
+                                                >
+                                                    {mode.label()}
+                                                </button>
+                                            }
+                                        })
+                                    }
+                                </div>
+                                <label class="block text-xs text-gray-500 mb-1">{"Also notify me for these words"}</label>
+                                <input
+                                    onchange={onkeywords}
+                                    value={props.settings.notifications.keywords.join(", ")}
+                                    placeholder="release, outage"
+                                    class="w-full px-2 py-1 text-sm bg-gray-100 rounded border border-gray-200 focus:outline-none focus:border-purple-400"
+                                />
+                            </>
+                        },
+                        NotificationPermission::Denied => html! {
+                            <div class="text-xs text-gray-500">
+                                {"Notifications are blocked. Allow them for this site in your browser's settings to turn them on."}
+                            </div>
+                        },
+                        _ => html! {
+                            <button
+                                onclick={onrequestnotifications}
+                                class="px-3 py-1 text-sm rounded bg-purple-600 text-white hover:bg-purple-700"
+                            >
+                                {"Turn on notifications"}
+                            </button>
+                        },
+                    }
+                }
+            </div>
+            <div class="p-4 border-t">
+                <label class="flex items-center text-sm text-gray-700 cursor-pointer select-none">
+                    <input
+                        type="checkbox"
+                        class="mr-2 accent-purple-600"
+                        checked={props.settings.sounds.enabled}
+                        onchange={toggle_sounds}
+                    />
+                    {"Play sounds while I'm in another window"}
+                </label>
+                if props.settings.sounds.enabled {
+                    <ul class="mt-2 space-y-1">
+                        {
+                            for SoundEvent::ALL.iter().map(|event| {
+                                let event = *event;
+                                let cue = *props.settings.sounds.cue(event);
+                                let ontoggle = {
+                                    let settings = props.settings.clone();
+                                    let onchange = props.onchange.clone();
+                                    Callback::from(move |_| {
+                                        let mut settings = settings.clone();
+                                        let cue = settings.sounds.cue_mut(event);
+                                        cue.enabled = !cue.enabled;
+                                        onchange.emit(settings);
+                                    })
+                                };
+                                let onvolume = {
+                                    let settings = props.settings.clone();
+                                    let onchange = props.onchange.clone();
+                                    Callback::from(move |e: Event| {
+                                        let input: HtmlInputElement = e.target_unchecked_into();
+                                        let mut settings = settings.clone();
+                                        settings.sounds.cue_mut(event).volume = input.value_as_number() as f32;
+                                        onchange.emit(settings);
+                                    })
+                                };
+                                let onpreview = props.onpreviewsound.reform(move |_: MouseEvent| (event, cue.volume));
+                                html! {
+                                    <li class="flex items-center gap-2 text-xs text-gray-700">
+                                        <input type="checkbox" class="accent-purple-600" checked={cue.enabled} onchange={ontoggle}/>
+                                        <span class="flex-1">{event.label()}</span>
+                                        <input
+                                            type="range"
+                                            min="0"
+                                            max="1"
+                                            step="0.05"
+                                            value={cue.volume.to_string()}
+                                            disabled={!cue.enabled}
+                                            onchange={onvolume}
+                                            class="w-20 accent-purple-600"
+                                            aria-label={format!("{} volume", event.label())}
+                                        />
+                                        <button onclick={onpreview} class="text-purple-600 hover:text-purple-800" title="Play">{"▶"}</button>
+                                    </li>
+                                }
+                            })
+                        }
+                    </ul>
+                }
+            </div>
+            <div class="p-4 border-t">
+                <div class="text-sm font-medium text-gray-700 mb-2">{"Do not disturb"}</div>
+                <label class="flex items-center text-xs text-gray-700 cursor-pointer select-none">
+                    <input
+                        type="checkbox"
+                        class="mr-2 accent-purple-600"
+                        checked={schedule.enabled}
+                        onchange={edit_settings(|s, _| s.dnd.schedule.enabled = !s.dnd.schedule.enabled)}
+                    />
+                    {"On a schedule"}
+                </label>
+                if schedule.enabled {
+                    <div class="mt-2 flex gap-1">
+                        {
+                            for DAY_LABELS.iter().enumerate().map(|(day, label)| {
+                                let onclick = {
+                                    let settings = props.settings.clone();
+                                    let onchange = props.onchange.clone();
+                                    Callback::from(move |_| {
+                                        let mut settings = settings.clone();
+                                        settings.dnd.schedule.days[day] = !settings.dnd.schedule.days[day];
+                                        onchange.emit(settings);
+                                    })
+                                };
+                                html! {
+                                    <button
+                                        {onclick}
+                                        class={classes!(
+                                            "w-7", "h-7", "text-xs", "rounded-full", "border", "transition-colors",
+                                            if schedule.days[day] { "border-purple-500 bg-purple-50" } else { "border-gray-200 hover:bg-gray-50" }
+                                        )}
+                                    >
+                                        {*label}
+                                    </button>
+                                }
+                            })
+                        }
+                    </div>
+                    <div class="mt-2 flex items-center gap-2 text-xs text-gray-700">
+                        {"From"}
+                        <input
+                            type="time"
+                            value={dnd::format_time(schedule.start)}
+                            onchange={edit_settings(|s, value| {
+                                if let Some(minutes) = dnd::parse_time(value) {
+                                    s.dnd.schedule.start = minutes;
+                                }
+                            })}
+                            class="px-1 bg-gray-100 rounded border border-gray-200"
+                        />
+                        {"to"}
+                        <input
+                            type="time"
+                            value={dnd::format_time(schedule.end)}
+                            onchange={edit_settings(|s, value| {
+                                if let Some(minutes) = dnd::parse_time(value) {
+                                    s.dnd.schedule.end = minutes;
+                                }
+                            })}
+                            class="px-1 bg-gray-100 rounded border border-gray-200"
+                        />
+                    </div>
+                }
+                <label class="mt-2 flex items-center text-xs text-gray-700 cursor-pointer select-none">
+                    <input
+                        type="checkbox"
+                        class="mr-2 accent-purple-600"
+                        checked={props.settings.dnd.allow_direct}
+                        onchange={edit_settings(|s, _| s.dnd.allow_direct = !s.dnd.allow_direct)}
+                    />
+                    {"Let direct messages through"}
+                </label>
+                <label class="block mt-2 text-xs text-gray-500 mb-1">{"Always let these people through"}</label>
+                <input
+                    onchange={edit_settings(|s, value| {
+                        s.dnd.allow_users = value
+                            .split(',')
+                            .map(|name| name.trim().to_string())
+                            .filter(|name| !name.is_empty())
+                            .collect();
+                    })}
+                    value={props.settings.dnd.allow_users.join(", ")}
+                    placeholder="alice, bob"
+                    class="w-full px-2 py-1 text-sm bg-gray-100 rounded border border-gray-200 focus:outline-none focus:border-purple-400"
+                />
+            </div>
+            if !props.settings.ignored.is_empty() {
+                <div class="p-4 border-t">
+                    <div class="text-sm font-medium text-gray-700 mb-2">{"Ignored users"}</div>
+                    <ul class="space-y-1">
+                        {
+                            for props.settings.ignored.iter().map(|name| {
+                                let onclick = {
+                                    let settings = props.settings.clone();
+                                    let onchange = props.onchange.clone();
+                                    let name = name.clone();
+                                    Callback::from(move |_| {
+                                        let mut settings = settings.clone();
+                                        settings.toggle_ignored(&name);
+                                        onchange.emit(settings);
+                                    })
+                                };
+                                html! {
+                                    <li class="flex justify-between items-center text-sm">
+                                        <span>{name.clone()}</span>
+                                        <button {onclick} class="text-xs text-purple-600 hover:underline">{"Unignore"}</button>
+                                    </li>
+                                }
+                            })
+                        }
+                    </ul>
+                </div>
+            }
+            <div class="p-4 border-t">
+                <div class="flex justify-between items-center mb-2">
+                    <div class="text-sm font-medium text-gray-700">{"Emoji packs"}</div>
+                    <label class="text-xs text-purple-600 hover:underline cursor-pointer">
+                        {"Import JSON…"}
+                        <input type="file" accept="application/json,.json" class="hidden" onchange={onimport}/>
+                    </label>
+                </div>
+                if props.packs.is_empty() {
+                    <div class="text-xs text-gray-500 italic">{"No custom packs installed"}</div>
+                } else {
+                    <ul class="space-y-1">
+                        {
+                            props.packs.iter().map(|pack| {
+                                let share = props.onsharepack.reform({
+                                    let name = pack.name.clone();
+                                    move |_: MouseEvent| name.clone()
+                                });
+                                let remove = props.onremovepack.reform({
+                                    let name = pack.name.clone();
+                                    move |_: MouseEvent| name.clone()
+                                });
+                                html! {
+                                    <li class="flex justify-between items-center text-sm">
+                                        <span>
+                                            {pack.name.clone()}
+                                            <span class="ml-1 text-xs text-gray-400">
+                                                {format!("{} emoji · {} stickers", pack.emoji.len(), pack.stickers.len())}
+                                            </span>
+                                        </span>
+                                        <span class="space-x-2 text-xs">
+                                            <button onclick={share} class="text-purple-600 hover:underline">{"Share"}</button>
+                                            <button onclick={remove} class="text-red-500 hover:underline">{"Remove"}</button>
+                                        </span>
+                                    </li>
+                                }
+                            }).collect::<Html>()
+                        }
+                    </ul>
+                }
+            </div>
+        </div>
+    }
+}
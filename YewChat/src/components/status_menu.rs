@@ -0,0 +1,120 @@
+use web_sys::HtmlInputElement;
+use yew::prelude::*;
+
+use crate::services::presence::{Presence, Status, MAX_STATUS_TEXT};
+
+#[derive(Properties, PartialEq)]
+pub struct StatusMenuProps {
+    pub presence: Presence,
+    pub onchange: Callback<Presence>,
+    /// Whether do not disturb was turned on by hand.
+    pub dnd: bool,
+    pub ondnd: Callback<bool>,
+    pub onclose: Callback<()>,
+}
+
+#[function_component(StatusMenu)]
+pub fn status_menu(props: &StatusMenuProps) -> Html {
+    let text = use_state(|| props.presence.text.clone().unwrap_or_default());
+
+    let oninput = {
+        let text = text.clone();
+        Callback::from(move |e: InputEvent| {
+            let input: HtmlInputElement = e.target_unchecked_into();
+            text.set(input.value());
+        })
+    };
+
+    let onsubmit = {
+        let onchange = props.onchange.clone();
+        let status = props.presence.status;
+        let text = text.clone();
+        Callback::from(move |e: FocusEvent| {
+            e.prevent_default();
+            let line = text.trim();
+            onchange.emit(Presence {
+                status,
+                text: (!line.is_empty()).then(|| line.to_string()),
+            });
+        })
+    };
+
+    let onclear = {
+        let onchange = props.onchange.clone();
+        let status = props.presence.status;
+        let text = text.clone();
+        Callback::from(move |_| {
+            text.set(String::new());
+            onchange.emit(Presence { status, text: None });
+        })
+    };
+
+    let ondnd = {
+        let ondnd = props.ondnd.clone();
+        let dnd = props.dnd;
+        Callback::from(move |_| ondnd.emit(!dnd))
+    };
+
+    let onclose = {
+        let onclose = props.onclose.clone();
+        Callback::from(move |_| onclose.emit(()))
+    };
+
+    html! {
+        <div class="absolute right-0 top-12 z-20 w-72 bg-white text-gray-800 rounded-xl shadow-2xl border border-gray-200">
+            <div class="flex justify-between items-center px-4 py-2 border-b">
+                <h2 class="text-sm font-semibold">{"Status"}</h2>
+                <button onclick={onclose} class="text-gray-400 hover:text-gray-600">{"✕"}</button>
+            </div>
+            <div class="py-1">
+                {
+                    for Status::ALL.iter().map(|status| {
+                        let status = *status;
+                        let selected = props.presence.status == status;
+                        let onclick = {
+                            let onchange = props.onchange.clone();
+                            let text = props.presence.text.clone();
+                            Callback::from(move |_| onchange.emit(Presence { status, text: text.clone() }))
+                        };
+                        html! {
+                            <button
+                                {onclick}
+                                class={classes!(
+                                    "w-full", "flex", "items-center", "px-4", "py-2", "text-sm", "hover:bg-gray-50",
+                                    if selected { "font-semibold" } else { "" }
+                                )}
+                            >
+                                <span class={classes!("w-2.5", "h-2.5", "rounded-full", "mr-3", status.color())}></span>
+                                {status.label()}
+                            </button>
+                        }
+                    })
+                }
+            </div>
+            <button
+                onclick={ondnd}
+                class="w-full flex items-center px-4 py-2 text-sm border-t hover:bg-gray-50"
+            >
+                <span class="mr-2">{"🌙"}</span>
+                { if props.dnd { "Resume notifications" } else { "Pause notifications" } }
+            </button>
+            <form class="p-3 border-t" {onsubmit}>
+                <input
+                    {oninput}
+                    value={(*text).clone()}
+                    maxlength={MAX_STATUS_TEXT.to_string()}
+                    placeholder="What's happening? :coffee:"
+                    class="w-full px-2 py-1 text-sm bg-gray-100 rounded border border-gray-200 focus:outline-none focus:border-purple-400"
+                />
+                <div class="mt-2 flex justify-end gap-2">
+                    <button type="button" onclick={onclear} class="px-3 py-1 text-xs rounded text-gray-600 hover:bg-gray-100">
+                        {"Clear"}
+                    </button>
+                    <button type="submit" class="px-3 py-1 text-xs rounded bg-purple-600 text-white hover:bg-purple-700">
+                        {"Set status"}
+                    </button>
+                </div>
+            </form>
+        </div>
+    }
+}
@@ -0,0 +1,394 @@
+use std::cmp::Ordering;
+use std::collections::HashSet;
+
+use web_sys::{HtmlInputElement, HtmlSelectElement};
+use yew::prelude::*;
+
+use crate::services::avatar::{avatar_url, AvatarStyle};
+use crate::services::presence::{Presence, Status};
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct UserListEntry {
+    pub name: String,
+    pub presence: Presence,
+    pub account: bool,
+    /// When they last posted, if they have on this device.
+    pub last_active: Option<f64>,
+    /// The earlier user this name looks like, if any.
+    pub lookalike_of: Option<String>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SortBy {
+    Name,
+    Status,
+    Activity,
+}
+
+impl SortBy {
+    pub const ALL: [SortBy; 3] = [SortBy::Name, SortBy::Status, SortBy::Activity];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            SortBy::Name => "Name",
+            SortBy::Status => "Status",
+            SortBy::Activity => "Activity",
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum GroupBy {
+    None,
+    Presence,
+    Role,
+}
+
+impl GroupBy {
+    pub const ALL: [GroupBy; 3] = [GroupBy::Presence, GroupBy::Role, GroupBy::None];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            GroupBy::None => "No groups",
+            GroupBy::Presence => "Presence",
+            GroupBy::Role => "Role",
+        }
+    }
+}
+
+#[derive(Properties, PartialEq)]
+pub struct UserListProps {
+    pub users: Vec<UserListEntry>,
+    pub current: String,
+    pub avatar_style: AvatarStyle,
+    /// Called with the name of the user picked by click or Enter.
+    pub onselect: Callback<String>,
+}
+
+/// Online users with a filter box, sorting, collapsible groups, and
+/// arrow-key navigation.
+#[function_component(UserList)]
+pub fn user_list(props: &UserListProps) -> Html {
+    let filter = use_state(String::new);
+    let sort = use_state(|| SortBy::Name);
+    let group = use_state(|| GroupBy::Presence);
+    let collapsed = use_state(HashSet::<&'static str>::new);
+    let cursor = use_state(|| None::<String>);
+    let filter_input = use_node_ref();
+
+    let groups = arrange(&props.users, &filter, *sort, *group);
+    // The rows the arrow keys move through, in display order.
+    let navigable: Vec<String> = groups
+        .iter()
+        .filter(|(title, _)| !collapsed.contains(title))
+        .flat_map(|(_, users)| users.iter().map(|u| u.name.clone()))
+        .collect();
+
+    {
+        let cursor = (*cursor).clone();
+        use_effect_with_deps(
+            move |cursor| {
+                if let Some(row) = cursor.as_ref().and_then(|name| {
+                    web_sys::window()?.document()?.get_element_by_id(&row_id(name))
+                }) {
+                    row.scroll_into_view_with_bool(false);
+                }
+                || ()
+            },
+            cursor,
+        );
+    }
+
+    let oninput = {
+        let filter = filter.clone();
+        let cursor = cursor.clone();
+        Callback::from(move |e: InputEvent| {
+            let input: HtmlInputElement = e.target_unchecked_into();
+            filter.set(input.value());
+            cursor.set(None);
+        })
+    };
+
+    let onkeydown = {
+        let cursor = cursor.clone();
+        let filter = filter.clone();
+        let onselect = props.onselect.clone();
+        let filter_input = filter_input.clone();
+        Callback::from(move |e: KeyboardEvent| {
+            let position = cursor
+                .as_deref()
+                .and_then(|name| navigable.iter().position(|n| n == name));
+            let last = navigable.len().checked_sub(1);
+            let next = match e.key().as_str() {
+                "ArrowDown" => position.map(|i| (i + 1).min(last.unwrap_or(0))).or(last.map(|_| 0)),
+                "ArrowUp" => position.map(|i| i.saturating_sub(1)).or(last),
+                "Enter" => {
+                    if let Some(name) = cursor.as_deref() {
+                        e.prevent_default();
+                        onselect.emit(name.to_string());
+                    }
+                    return;
+                }
+                "Escape" => {
+                    e.prevent_default();
+                    filter.set(String::new());
+                    cursor.set(None);
+                    if let Some(input) = filter_input.cast::<HtmlInputElement>() {
+                        input.set_value("");
+                        let _ = input.focus();
+                    }
+                    return;
+                }
+                _ => return,
+            };
+            e.prevent_default();
+            cursor.set(next.map(|i| navigable[i].clone()));
+        })
+    };
+
+    html! {
+        <div {onkeydown}>
+            <div class="p-2 space-y-2 border-b">
+                <input
+                    ref={filter_input}
+                    {oninput}
+                    type="search"
+                    placeholder="Filter people…"
+                    aria-label="Filter people"
+                    class="w-full px-2 py-1 text-sm bg-gray-100 rounded border border-gray-200 focus:outline-none focus:border-purple-400"
+                />
+                <div class="flex gap-2 text-xs text-gray-600">
+                    <label class="flex items-center gap-1">
+                        {"Sort"}
+                        <select
+                            class="bg-transparent border-b border-gray-300 focus:outline-none"
+                            onchange={{
+                                let sort = sort.clone();
+                                Callback::from(move |e: Event| {
+                                    let select: HtmlSelectElement = e.target_unchecked_into();
+                                    if let Some(choice) = SortBy::ALL.iter().find(|s| s.label() == select.value()) {
+                                        sort.set(*choice);
+                                    }
+                                })
+                            }}
+                        >
+                            {
+                                for SortBy::ALL.iter().map(|s| html! {
+                                    <option value={s.label()} selected={*s == *sort}>{s.label()}</option>
+                                })
+                            }
+                        </select>
+                    </label>
+                    <label class="flex items-center gap-1">
+                        {"Group"}
+                        <select
+                            class="bg-transparent border-b border-gray-300 focus:outline-none"
+                            onchange={{
+                                let group = group.clone();
+                                Callback::from(move |e: Event| {
+                                    let select: HtmlSelectElement = e.target_unchecked_into();
+                                    if let Some(choice) = GroupBy::ALL.iter().find(|g| g.label() == select.value()) {
+                                        group.set(*choice);
+                                    }
+                                })
+                            }}
+                        >
+                            {
+                                for GroupBy::ALL.iter().map(|g| html! {
+                                    <option value={g.label()} selected={*g == *group}>{g.label()}</option>
+                                })
+                            }
+                        </select>
+                    </label>
+                </div>
+            </div>
+            if groups.is_empty() {
+                <div class="p-4 text-center text-gray-500 italic">
+                    { if filter.is_empty() { "No users online" } else { "Nobody matches" } }
+                </div>
+            }
+            <div
+                role="listbox"
+                tabindex="0"
+                aria-label="Online users"
+                aria-activedescendant={cursor.as_deref().map(row_id)}
+                class="focus:outline-none"
+            >
+                {
+                    for groups.into_iter().map(|(title, users)| {
+                        let is_collapsed = collapsed.contains(title);
+                        let toggle = {
+                            let collapsed = collapsed.clone();
+                            Callback::from(move |_| {
+                                let mut next = (*collapsed).clone();
+                                if !next.remove(title) {
+                                    next.insert(title);
+                                }
+                                collapsed.set(next);
+                            })
+                        };
+                        html! {
+                            <section>
+                                if !title.is_empty() {
+                                    <button
+                                        onclick={toggle}
+                                        aria-expanded={(!is_collapsed).to_string()}
+                                        class="w-full flex items-center px-3 pt-3 pb-1 text-xs font-semibold uppercase tracking-wide text-gray-400 hover:text-gray-600"
+                                    >
+                                        <span class="w-4">{ if is_collapsed { "▸" } else { "▾" } }</span>
+                                        {format!("{} — {}", title, users.len())}
+                                    </button>
+                                }
+                                if !is_collapsed {
+                                    <ul class="divide-y">
+                                        {
+                                            for users.into_iter().map(|u| {
+                                                let is_current = u.name == props.current;
+                                                let focused = cursor.as_deref() == Some(u.name.as_str());
+                                                view_row(u, is_current, focused, props.avatar_style, &props.onselect)
+                                            })
+                                        }
+                                    </ul>
+                                }
+                            </section>
+                        }
+                    })
+                }
+            </div>
+        </div>
+    }
+}
+
+fn view_row(
+    user: &UserListEntry,
+    is_current: bool,
+    focused: bool,
+    avatar_style: AvatarStyle,
+    onselect: &Callback<String>,
+) -> Html {
+    let onclick = {
+        let onselect = onselect.clone();
+        let name = user.name.clone();
+        Callback::from(move |_| onselect.emit(name.clone()))
+    };
+    html! {
+        <li
+            id={row_id(&user.name)}
+            role="option"
+            aria-selected={focused.to_string()}
+            {onclick}
+            class={classes!(
+                "p-3", "hover:bg-gray-50", "cursor-pointer",
+                if is_current { "bg-purple-50" } else { "" },
+                if focused { "ring-2 ring-inset ring-purple-400" } else { "" }
+            )}
+        >
+            <div class="flex items-center space-x-3">
+                <div class="relative">
+                    <img class="w-10 h-10 rounded-full" src={avatar_url(&user.name, avatar_style)} alt="avatar"/>
+                    <div class={classes!(
+                        "absolute", "bottom-0", "right-0", "w-3", "h-3", "rounded-full", "border-2", "border-white",
+                        user.presence.status.color()
+                    )}></div>
+                </div>
+                <div class="min-w-0">
+                    <div class="text-sm font-medium text-gray-900 flex items-center">
+                        {user.name.clone()}
+                        {lookalike_marker(user.lookalike_of.as_deref())}
+                        if is_current {
+                            <span class="ml-2 text-xs text-purple-600">{" (you)"}</span>
+                        }
+                    </div>
+                    <div class="text-xs text-gray-500 truncate">
+                        {
+                            match &user.presence.text {
+                                Some(text) => format!("{} · {}", user.presence.status.label(), text),
+                                None => user.presence.status.label().to_string(),
+                            }
+                        }
+                    </div>
+                </div>
+            </div>
+        </li>
+    }
+}
+
+pub fn lookalike_marker(original: Option<&str>) -> Html {
+    match original {
+        Some(original) => html! {
+            <span
+                class="ml-1 inline-flex items-center rounded bg-amber-100 px-1 text-xs font-normal text-amber-700"
+                title={format!("This name looks like {}; it may not be who you think", original)}
+            >
+                {"⚠ lookalike"}
+            </span>
+        },
+        None => html! {},
+    }
+}
+
+fn row_id(name: &str) -> String {
+    format!("user-row-{}", name)
+}
+
+/// Filters, sorts and groups `users`. Empty groups are left out; with no
+/// grouping there is a single group with an empty title.
+fn arrange<'a>(
+    users: &'a [UserListEntry],
+    filter: &str,
+    sort: SortBy,
+    group: GroupBy,
+) -> Vec<(&'static str, Vec<&'a UserListEntry>)> {
+    let needle = filter.trim().to_lowercase();
+    let mut matching: Vec<&UserListEntry> = users
+        .iter()
+        .filter(|u| {
+            needle.is_empty()
+                || u.name.to_lowercase().contains(&needle)
+                || u.presence
+                    .text
+                    .as_ref()
+                    .is_some_and(|text| text.to_lowercase().contains(&needle))
+        })
+        .collect();
+    matching.sort_by(|a, b| compare(a, b, sort));
+
+    let titles: Vec<&'static str> = match group {
+        GroupBy::None => vec![""],
+        GroupBy::Presence => Status::ALL.iter().map(|s| s.label()).collect(),
+        GroupBy::Role => vec!["Members", "Guests"],
+    };
+    let title_of = |user: &UserListEntry| match group {
+        GroupBy::None => "",
+        GroupBy::Presence => user.presence.status.label(),
+        GroupBy::Role if user.account => "Members",
+        GroupBy::Role => "Guests",
+    };
+    titles
+        .into_iter()
+        .map(|title| {
+            let members: Vec<&UserListEntry> = matching
+                .iter()
+                .copied()
+                .filter(|u| title_of(u) == title)
+                .collect();
+            (title, members)
+        })
+        .filter(|(_, members)| !members.is_empty())
+        .collect()
+}
+
+fn compare(a: &UserListEntry, b: &UserListEntry, sort: SortBy) -> Ordering {
+    let by_name = || a.name.to_lowercase().cmp(&b.name.to_lowercase());
+    let status_rank = |u: &UserListEntry| Status::ALL.iter().position(|s| *s == u.presence.status);
+    match sort {
+        SortBy::Name => by_name(),
+        SortBy::Status => status_rank(a).cmp(&status_rank(b)).then_with(by_name),
+        // Most recent first; people who haven't posted go last.
+        SortBy::Activity => b
+            .last_active
+            .unwrap_or(f64::NEG_INFINITY)
+            .total_cmp(&a.last_active.unwrap_or(f64::NEG_INFINITY))
+            .then_with(by_name),
+    }
+}
@@ -2,17 +2,17 @@
 
 mod components;
 mod services;
-
-use std::cell::RefCell;
-use std::rc::Rc;
+#[cfg(all(test, target_arch = "wasm32"))]
+mod test_support;
 
 use wasm_bindgen::prelude::*;
-use yew::functional::*;
 use yew::prelude::*;
 use yew_router::prelude::*;
 
 use components::chat::Chat;
+use components::guard::RequireUser;
 use components::login::Login;
+use components::profile::ProfilePage;
 
 // When the `wee_alloc` feature is enabled, this uses `wee_alloc` as the global
 // allocator.
@@ -22,47 +22,43 @@ use components::login::Login;
 #[global_allocator]
 static ALLOC: wee_alloc::WeeAlloc = wee_alloc::WeeAlloc::INIT;
 
-#[derive(Debug, Clone, Copy, PartialEq, Routable)]
+#[derive(Debug, Clone, PartialEq, Routable)]
 pub enum Route {
     #[at("/")]
     Login,
     #[at("/chat")]
     Chat,
+    #[at("/user/:name")]
+    Profile { name: String },
     #[not_found]
     #[at("/404")]
     NotFound,
 }
 
-pub type User = Rc<UserInner>;
-
-#[derive(Debug, PartialEq)]
-pub struct UserInner {
-    pub username: RefCell<String>,
-}
-
 #[function_component(Main)]
 fn main() -> Html {
-    let ctx = use_state(|| {
-        Rc::new(UserInner {
-            username: RefCell::new("initial".into()),
-        })
-    });
-
     html! {
-        <ContextProvider<User> context={(*ctx).clone()}>
-            <BrowserRouter>
-                <div class="flex w-screen h-screen bg-gray-50">
-                    <Switch<Route> render={Switch::render(switch)}/>
-                </div>
-            </BrowserRouter>
-        </ContextProvider<User>>
+        <BrowserRouter>
+            <div class="flex w-screen h-screen bg-gray-50">
+                <Switch<Route> render={Switch::render(switch)}/>
+            </div>
+        </BrowserRouter>
     }
 }
 
 fn switch(selected_route: &Route) -> Html {
     match selected_route {
         Route::Login => html! {<Login />},
-        Route::Chat => html! {<Chat/>},
+        Route::Chat => html! {
+            <RequireUser>
+                <Chat/>
+            </RequireUser>
+        },
+        Route::Profile { name } => html! {
+            <RequireUser>
+                <ProfilePage name={name.clone()}/>
+            </RequireUser>
+        },
         Route::NotFound => html! {
             <div class="flex flex-col items-center justify-center h-screen bg-gradient-to-br from-purple-800 to-indigo-900 text-white p-4">
                 <svg xmlns="http://www.w3.org/2000/svg" class="h-24 w-24 text-purple-300 mb-6" fill="none" viewBox="0 0 24 24" stroke="currentColor">
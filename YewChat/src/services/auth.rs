@@ -0,0 +1,89 @@
+use reqwasm::http::{Request, Response};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+
+/// Auth endpoint; the development server serves a stand-in next to the socket.
+const AUTH_URL: &str = "http://127.0.0.1:8080/auth";
+/// How long before expiry a token is swapped for a fresh one.
+const REFRESH_MARGIN_MS: f64 = 60_000.0;
+
+/// A signed token proving who the user is, sent with `register`.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AuthToken {
+    pub token: String,
+    /// Milliseconds since the Unix epoch.
+    pub expires_at: f64,
+}
+
+impl AuthToken {
+    pub fn is_expired(&self) -> bool {
+        self.expires_at <= js_sys::Date::now()
+    }
+
+    /// Milliseconds to wait before refreshing, leaving a safety margin.
+    pub fn refresh_delay_ms(&self) -> u32 {
+        (self.expires_at - REFRESH_MARGIN_MS - js_sys::Date::now()).clamp(0.0, u32::MAX as f64) as u32
+    }
+}
+
+#[derive(Deserialize)]
+struct ErrorBody {
+    error: String,
+}
+
+pub async fn sign_in_with_password(username: &str, password: &str) -> Result<AuthToken, String> {
+    let body = json!({ "username": username, "password": password });
+    token_from(post("/password", &body.to_string(), None).await?).await
+}
+
+/// Asks the auth service to deliver a one-time code to the user.
+pub async fn request_code(username: &str) -> Result<(), String> {
+    let body = json!({ "username": username });
+    let response = post("/code/request", &body.to_string(), None).await?;
+    if response.ok() {
+        Ok(())
+    } else {
+        Err(error_from(response).await)
+    }
+}
+
+pub async fn sign_in_with_code(username: &str, code: &str) -> Result<AuthToken, String> {
+    let body = json!({ "username": username, "code": code });
+    token_from(post("/code/verify", &body.to_string(), None).await?).await
+}
+
+/// Exchanges a still-valid token for one with a later expiry.
+pub async fn refresh(token: &AuthToken) -> Result<AuthToken, String> {
+    token_from(post("/refresh", "{}", Some(&token.token)).await?).await
+}
+
+async fn post(path: &str, body: &str, bearer: Option<&str>) -> Result<Response, String> {
+    let mut request = Request::post(&format!("{}{}", AUTH_URL, path))
+        .header("Content-Type", "application/json")
+        .body(body);
+    if let Some(token) = bearer {
+        request = request.header("Authorization", &format!("Bearer {}", token));
+    }
+    request
+        .send()
+        .await
+        .map_err(|e| format!("Could not reach the sign-in service: {}", e))
+}
+
+async fn token_from(response: Response) -> Result<AuthToken, String> {
+    if !response.ok() {
+        return Err(error_from(response).await);
+    }
+    response
+        .json()
+        .await
+        .map_err(|e| format!("Unexpected answer from the sign-in service: {}", e))
+}
+
+async fn error_from(response: Response) -> String {
+    match response.json::<ErrorBody>().await {
+        Ok(body) => body.error,
+        Err(_) => format!("Sign-in failed ({})", response.status()),
+    }
+}
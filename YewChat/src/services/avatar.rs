@@ -0,0 +1,161 @@
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AvatarStyle {
+    /// Mirrored 5x5 block pattern on a tinted background.
+    #[default]
+    Identicon,
+    /// Up to two initials on a solid colored circle.
+    Initials,
+    /// Faded identicon pattern with the initials on top.
+    Badge,
+}
+
+impl AvatarStyle {
+    pub const ALL: [AvatarStyle; 3] = [
+        AvatarStyle::Identicon,
+        AvatarStyle::Initials,
+        AvatarStyle::Badge,
+    ];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            AvatarStyle::Identicon => "Identicon",
+            AvatarStyle::Initials => "Initials",
+            AvatarStyle::Badge => "Badge",
+        }
+    }
+}
+
+const SIZE: u32 = 5;
+const CELL: u32 = 8;
+const CANVAS: u32 = SIZE * CELL;
+
+/// Returns an `<img src>`-ready data URI containing the avatar for `name`.
+///
+/// Every avatar in the app goes through this function, so the same username
+/// always renders the same image without any network access.
+pub fn avatar_url(name: &str, style: AvatarStyle) -> String {
+    format!(
+        "data:image/svg+xml;charset=utf-8,{}",
+        encode_uri_component(&avatar_svg(name, style))
+    )
+}
+
+pub fn avatar_svg(name: &str, style: AvatarStyle) -> String {
+    let hash = fnv1a(name.trim().to_lowercase().as_bytes());
+    let hue = (hash % 360) as u32;
+    let foreground = format!("hsl({},65%,50%)", hue);
+    let background = format!("hsl({},60%,92%)", hue);
+
+    let mut body = String::new();
+    match style {
+        AvatarStyle::Identicon => {
+            body.push_str(&rect(0, 0, CANVAS, &background, 1.0));
+            body.push_str(&pattern(hash, &foreground, 1.0));
+        }
+        AvatarStyle::Initials => {
+            body.push_str(&rect(0, 0, CANVAS, &foreground, 1.0));
+            body.push_str(&initials_text(name, "#ffffff"));
+        }
+        AvatarStyle::Badge => {
+            body.push_str(&rect(0, 0, CANVAS, &foreground, 1.0));
+            body.push_str(&pattern(hash, "#ffffff", 0.25));
+            body.push_str(&initials_text(name, "#ffffff"));
+        }
+    }
+
+    format!(
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" viewBox=\"0 0 {size} {size}\" width=\"{size}\" height=\"{size}\">{}</svg>",
+        body,
+        size = CANVAS
+    )
+}
+
+/// Up to two uppercase initials: the first letters of the first two words,
+/// or the first two letters of a single word.
+pub fn initials(name: &str) -> String {
+    let words: Vec<&str> = name
+        .split(|c: char| c.is_whitespace() || c == '_' || c == '-' || c == '.')
+        .filter(|w| !w.is_empty())
+        .collect();
+
+    let letters: String = match words.as_slice() {
+        [] => "?".into(),
+        [single] => single.chars().take(2).collect(),
+        [first, second, ..] => first.chars().take(1).chain(second.chars().take(1)).collect(),
+    };
+    letters.to_uppercase()
+}
+
+fn pattern(hash: u64, fill: &str, opacity: f32) -> String {
+    // Only the left three columns are taken from the hash; the right two
+    // mirror them so the result is symmetric like a classic identicon.
+    let mut cells = String::new();
+    for row in 0..SIZE {
+        for col in 0..SIZE.div_ceil(2) {
+            let bit = row * 3 + col;
+            if (hash >> (bit + 8)) & 1 == 1 {
+                cells.push_str(&rect(col * CELL, row * CELL, CELL, fill, opacity));
+                let mirrored = SIZE - 1 - col;
+                if mirrored != col {
+                    cells.push_str(&rect(mirrored * CELL, row * CELL, CELL, fill, opacity));
+                }
+            }
+        }
+    }
+    cells
+}
+
+fn rect(x: u32, y: u32, size: u32, fill: &str, opacity: f32) -> String {
+    format!(
+        "<rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\" fill=\"{}\" fill-opacity=\"{}\"/>",
+        x, y, size, size, fill, opacity
+    )
+}
+
+fn initials_text(name: &str, fill: &str) -> String {
+    format!(
+        "<text x=\"50%\" y=\"50%\" dy=\".35em\" text-anchor=\"middle\" font-family=\"sans-serif\" font-size=\"16\" font-weight=\"bold\" fill=\"{}\">{}</text>",
+        fill,
+        escape_xml(&initials(name))
+    )
+}
+
+fn fnv1a(bytes: &[u8]) -> u64 {
+    let mut hash: u64 = 0xcbf29ce484222325;
+    for b in bytes {
+        hash ^= *b as u64;
+        hash = hash.wrapping_mul(0x100000001b3);
+    }
+    hash
+}
+
+fn escape_xml(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&apos;"),
+            _ => out.push(c),
+        }
+    }
+    out
+}
+
+fn encode_uri_component(s: &str) -> String {
+    let mut out = String::with_capacity(s.len() * 2);
+    for b in s.bytes() {
+        match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => {
+                out.push(b as char)
+            }
+            _ => out.push_str(&format!("%{:02X}", b)),
+        }
+    }
+    out
+}
@@ -0,0 +1,384 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum CommandAction {
+    Send(String),
+    /// Send the text as an action, rendered as "* alice waves".
+    Emote(String),
+    Rename(String),
+    /// Mark the current user as away, with an optional reason.
+    Away(Option<String>),
+    /// Send a private message that only `to` and the sender can see.
+    Direct { to: String, text: String },
+    ClearTimeline,
+    Help,
+}
+
+/// Arguments following the command name, split on whitespace with support
+/// for `"double quoted"` words.
+#[derive(Clone, Debug, PartialEq)]
+pub struct CommandArgs {
+    pub raw: String,
+    pub words: Vec<String>,
+    /// Byte offset in `raw` just past each word.
+    word_ends: Vec<usize>,
+}
+
+impl CommandArgs {
+    pub fn parse(raw: &str) -> Self {
+        let raw = raw.trim();
+        let mut words = vec![];
+        let mut word_ends = vec![];
+        let mut current = String::new();
+        let mut quoted = false;
+        for (i, c) in raw.char_indices() {
+            match c {
+                '"' => quoted = !quoted,
+                c if c.is_whitespace() && !quoted => {
+                    if !current.is_empty() {
+                        words.push(std::mem::take(&mut current));
+                        word_ends.push(i);
+                    }
+                }
+                c => current.push(c),
+            }
+        }
+        if !current.is_empty() {
+            words.push(current);
+            word_ends.push(raw.len());
+        }
+
+        Self {
+            raw: raw.to_string(),
+            words,
+            word_ends,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.raw.is_empty()
+    }
+
+    /// The raw text after the first `n` words, split the same way as `words`.
+    pub fn rest_after(&self, n: usize) -> String {
+        match n.checked_sub(1) {
+            None => self.raw.clone(),
+            Some(last) => self
+                .word_ends
+                .get(last)
+                .map(|&end| self.raw[end..].trim().to_string())
+                .unwrap_or_default(),
+        }
+    }
+}
+
+/// A command that can be typed into the composer as `/name args`.
+pub trait SlashCommand {
+    fn name(&self) -> &'static str;
+    /// Usage hint shown in autocomplete, e.g. `"/dm <user> <message>"`.
+    fn usage(&self) -> &'static str;
+    fn description(&self) -> &'static str;
+    fn run(&self, args: &CommandArgs) -> Result<CommandAction, String>;
+}
+
+pub struct CommandRegistry {
+    commands: Vec<Rc<dyn SlashCommand>>,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum Parsed {
+    Text(String),
+    Action(CommandAction),
+    /// A command was recognised but rejected, or no command matched.
+    Error(String),
+}
+
+impl CommandRegistry {
+    pub fn with_builtins() -> Self {
+        let mut registry = Self { commands: vec![] };
+        registry.register(Me);
+        registry.register(Nick);
+        registry.register(Shrug);
+        registry.register(Clear);
+        registry.register(Help);
+        registry.register(Away);
+        registry.register(Dm);
+        registry
+    }
+
+    pub fn register(&mut self, command: impl SlashCommand + 'static) {
+        self.commands.retain(|c| c.name() != command.name());
+        self.commands.push(Rc::new(command));
+    }
+
+    pub fn find(&self, name: &str) -> Option<Rc<dyn SlashCommand>> {
+        let name = name.to_lowercase();
+        self.commands.iter().find(|c| c.name() == name).cloned()
+    }
+
+    pub fn commands(&self) -> Vec<Rc<dyn SlashCommand>> {
+        self.commands.clone()
+    }
+
+    pub fn parse(&self, input: &str) -> Parsed {
+        let input = input.trim();
+        // `//text` escapes a message that really starts with a slash.
+        if let Some(escaped) = input.strip_prefix("//") {
+            return Parsed::Text(format!("/{}", escaped));
+        }
+        let body = match input.strip_prefix('/') {
+            Some(body) => body,
+            None => return Parsed::Text(input.to_string()),
+        };
+
+        let (name, args) = match body.find(char::is_whitespace) {
+            Some(i) => (&body[..i], &body[i..]),
+            None => (body, ""),
+        };
+        match self.find(name) {
+            Some(command) => match command.run(&CommandArgs::parse(args)) {
+                Ok(action) => Parsed::Action(action),
+                Err(e) => Parsed::Error(format!("{} (usage: {})", e, command.usage())),
+            },
+            None => Parsed::Error(format!(
+                "Unknown command /{}. Type /help to see what's available.",
+                name
+            )),
+        }
+    }
+
+    /// Commands to suggest while the composer holds `input`.
+    ///
+    /// While the name is still being typed every command with that prefix is
+    /// returned; once a space follows, only the exact match is kept so its
+    /// usage can be shown as a hint.
+    pub fn suggestions(&self, input: &str) -> Vec<Rc<dyn SlashCommand>> {
+        let body = match input.strip_prefix('/') {
+            Some(body) if !body.starts_with('/') => body,
+            _ => return vec![],
+        };
+        match body.find(char::is_whitespace) {
+            Some(i) => self.find(&body[..i]).into_iter().collect(),
+            None => {
+                let prefix = body.to_lowercase();
+                self.commands
+                    .iter()
+                    .filter(|c| c.name().starts_with(&prefix))
+                    .cloned()
+                    .collect()
+            }
+        }
+    }
+}
+
+thread_local! {
+    static REGISTRY: RefCell<CommandRegistry> = RefCell::new(CommandRegistry::with_builtins());
+}
+
+pub fn with_registry<R>(f: impl FnOnce(&CommandRegistry) -> R) -> R {
+    REGISTRY.with(|registry| f(&registry.borrow()))
+}
+
+struct Me;
+
+impl SlashCommand for Me {
+    fn name(&self) -> &'static str {
+        "me"
+    }
+    fn usage(&self) -> &'static str {
+        "/me <action>"
+    }
+    fn description(&self) -> &'static str {
+        "Describe what you're doing"
+    }
+    fn run(&self, args: &CommandArgs) -> Result<CommandAction, String> {
+        if args.is_empty() {
+            return Err("Say what you're doing".into());
+        }
+        Ok(CommandAction::Emote(args.raw.clone()))
+    }
+}
+
+struct Nick;
+
+impl SlashCommand for Nick {
+    fn name(&self) -> &'static str {
+        "nick"
+    }
+    fn usage(&self) -> &'static str {
+        "/nick <new name>"
+    }
+    fn description(&self) -> &'static str {
+        "Change your nickname"
+    }
+    fn run(&self, args: &CommandArgs) -> Result<CommandAction, String> {
+        if args.is_empty() {
+            return Err("Missing the new name".into());
+        }
+        Ok(CommandAction::Rename(args.raw.clone()))
+    }
+}
+
+struct Shrug;
+
+impl SlashCommand for Shrug {
+    fn name(&self) -> &'static str {
+        "shrug"
+    }
+    fn usage(&self) -> &'static str {
+        "/shrug [message]"
+    }
+    fn description(&self) -> &'static str {
+        "Append ¯\\_(ツ)_/¯ to your message"
+    }
+    fn run(&self, args: &CommandArgs) -> Result<CommandAction, String> {
+        let shrug = "¯\\_(ツ)_/¯";
+        if args.is_empty() {
+            Ok(CommandAction::Send(shrug.into()))
+        } else {
+            Ok(CommandAction::Send(format!("{} {}", args.raw, shrug)))
+        }
+    }
+}
+
+struct Clear;
+
+impl SlashCommand for Clear {
+    fn name(&self) -> &'static str {
+        "clear"
+    }
+    fn usage(&self) -> &'static str {
+        "/clear"
+    }
+    fn description(&self) -> &'static str {
+        "Clear the timeline"
+    }
+    fn run(&self, _args: &CommandArgs) -> Result<CommandAction, String> {
+        Ok(CommandAction::ClearTimeline)
+    }
+}
+
+struct Help;
+
+impl SlashCommand for Help {
+    fn name(&self) -> &'static str {
+        "help"
+    }
+    fn usage(&self) -> &'static str {
+        "/help"
+    }
+    fn description(&self) -> &'static str {
+        "List available commands"
+    }
+    fn run(&self, _args: &CommandArgs) -> Result<CommandAction, String> {
+        Ok(CommandAction::Help)
+    }
+}
+
+struct Away;
+
+impl SlashCommand for Away {
+    fn name(&self) -> &'static str {
+        "away"
+    }
+    fn usage(&self) -> &'static str {
+        "/away [reason]"
+    }
+    fn description(&self) -> &'static str {
+        "Let others know you've stepped away"
+    }
+    fn run(&self, args: &CommandArgs) -> Result<CommandAction, String> {
+        Ok(CommandAction::Away(if args.is_empty() {
+            None
+        } else {
+            Some(args.raw.clone())
+        }))
+    }
+}
+
+struct Dm;
+
+impl SlashCommand for Dm {
+    fn name(&self) -> &'static str {
+        "dm"
+    }
+    fn usage(&self) -> &'static str {
+        "/dm <user> <message>"
+    }
+    fn description(&self) -> &'static str {
+        "Send a private message"
+    }
+    fn run(&self, args: &CommandArgs) -> Result<CommandAction, String> {
+        let to = match args.words.first() {
+            Some(to) => to.trim_start_matches('@').to_string(),
+            None => return Err("Missing the recipient".into()),
+        };
+        let text = args.rest_after(1);
+        if text.is_empty() {
+            return Err("Missing the message".into());
+        }
+        Ok(CommandAction::Direct { to, text })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn words_split_on_whitespace_and_quotes() {
+        let args = CommandArgs::parse("  bob   \"hello there\" x ");
+        assert_eq!(args.raw, "bob   \"hello there\" x");
+        assert_eq!(args.words, ["bob", "hello there", "x"]);
+    }
+
+    #[test]
+    fn rest_keeps_the_text_after_a_quoted_word() {
+        let args = CommandArgs::parse("\"bob smith\" see you at  5");
+        assert_eq!(args.words[0], "bob smith");
+        assert_eq!(args.rest_after(1), "see you at  5");
+        assert_eq!(args.rest_after(2), "you at  5");
+        assert_eq!(args.rest_after(0), args.raw);
+    }
+
+    #[test]
+    fn rest_keeps_quotes_in_the_message() {
+        let args = CommandArgs::parse("bob \"quoted\" reply");
+        assert_eq!(args.rest_after(1), "\"quoted\" reply");
+        assert_eq!(args.rest_after(3), "");
+        assert_eq!(args.rest_after(4), "");
+    }
+
+    #[test]
+    fn empty_input_has_no_words() {
+        let args = CommandArgs::parse("   ");
+        assert!(args.is_empty());
+        assert!(args.words.is_empty());
+        assert_eq!(args.rest_after(1), "");
+    }
+
+    #[test]
+    fn dm_takes_the_quoted_recipient_and_the_rest() {
+        let registry = CommandRegistry::with_builtins();
+        assert_eq!(
+            registry.parse("/dm \"@bob smith\" hi  there"),
+            Parsed::Action(CommandAction::Direct {
+                to: "bob smith".into(),
+                text: "hi  there".into(),
+            })
+        );
+        assert!(matches!(registry.parse("/dm bob"), Parsed::Error(_)));
+    }
+
+    #[test]
+    fn unknown_commands_are_errors() {
+        let registry = CommandRegistry::with_builtins();
+        assert_eq!(
+            registry.parse("/frobnicate now"),
+            Parsed::Error("Unknown command /frobnicate. Type /help to see what's available.".into())
+        );
+        assert_eq!(registry.parse("//frobnicate"), Parsed::Text("/frobnicate".into()));
+        assert_eq!(registry.parse(" hello "), Parsed::Text("hello".into()));
+    }
+}
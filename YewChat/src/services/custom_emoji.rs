@@ -0,0 +1,163 @@
+use std::collections::BTreeMap;
+
+use gloo::storage::{LocalStorage, Storage};
+use serde::{Deserialize, Serialize};
+
+const STORAGE_KEY: &str = "yewchat.emoji.packs";
+const MAX_NAME_LEN: usize = 32;
+/// Most emoji and stickers a pack may hold, together.
+const MAX_PACK_ENTRIES: usize = 200;
+/// Largest total size of a pack's images, in bytes of data URL.
+const MAX_PACK_BYTES: usize = 2 * 1024 * 1024;
+
+/// A team-defined set of custom emoji and stickers.
+///
+/// Manifest format, as imported from a JSON file or shared over the socket:
+///
+/// ```json
+/// {
+///   "name": "Parrots",
+///   "emoji": { "partyparrot": "data:image/gif;base64,..." },
+///   "stickers": { "wave": "data:image/png;base64,..." }
+/// }
+/// ```
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct EmojiPack {
+    pub name: String,
+    #[serde(default)]
+    pub emoji: BTreeMap<String, String>,
+    #[serde(default)]
+    pub stickers: BTreeMap<String, String>,
+}
+
+/// A pack broadcast by another user; the server fills in `from`.
+#[derive(Clone, Deserialize)]
+pub struct SharedPack {
+    pub from: String,
+    pub pack: EmojiPack,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Segment<'a> {
+    Text(&'a str),
+    Emoji { name: &'a str, url: &'a str },
+}
+
+pub fn parse_manifest(json: &str) -> Result<EmojiPack, String> {
+    let pack: EmojiPack =
+        serde_json::from_str(json).map_err(|e| format!("Invalid emoji pack: {}", e))?;
+    validate(&pack)?;
+    Ok(pack)
+}
+
+pub fn validate(pack: &EmojiPack) -> Result<(), String> {
+    if pack.name.trim().is_empty() {
+        return Err("Emoji pack has no name".into());
+    }
+    if pack.emoji.is_empty() && pack.stickers.is_empty() {
+        return Err(format!("Emoji pack {} is empty", pack.name));
+    }
+    if pack.emoji.len() + pack.stickers.len() > MAX_PACK_ENTRIES {
+        return Err(format!(
+            "Emoji pack {} has more than {} entries",
+            pack.name, MAX_PACK_ENTRIES
+        ));
+    }
+    let size: usize = pack.emoji.values().chain(pack.stickers.values()).map(String::len).sum();
+    if size > MAX_PACK_BYTES {
+        return Err(format!("Emoji pack {} is larger than 2 MB", pack.name));
+    }
+    for (name, url) in pack.emoji.iter().chain(pack.stickers.iter()) {
+        if !is_valid_name(name) {
+            return Err(format!(
+                "\"{}\" is not a valid emoji name; use a-z, 0-9, _, + or -",
+                name
+            ));
+        }
+        if !url.starts_with("data:image/") {
+            return Err(format!(":{}: must be an image data URL", name));
+        }
+    }
+    Ok(())
+}
+
+fn is_valid_name(name: &str) -> bool {
+    !name.is_empty()
+        && name.len() <= MAX_NAME_LEN
+        && name
+            .chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_' || c == '+' || c == '-')
+}
+
+pub fn load_packs() -> Vec<EmojiPack> {
+    LocalStorage::get(STORAGE_KEY).unwrap_or_default()
+}
+
+pub fn save_packs(packs: &[EmojiPack]) {
+    if let Err(e) = LocalStorage::set(STORAGE_KEY, packs) {
+        log::error!("failed to cache emoji packs: {:?}", e);
+    }
+}
+
+pub fn is_installed(packs: &[EmojiPack], name: &str) -> bool {
+    packs.iter().any(|p| p.name == name)
+}
+
+/// Adds `pack` and caches the result. A pack with the same name is only
+/// replaced when `replace` is set.
+pub fn install(packs: &mut Vec<EmojiPack>, pack: EmojiPack, replace: bool) -> Result<(), String> {
+    if is_installed(packs, &pack.name) {
+        if !replace {
+            return Err(format!("You already have an emoji pack named \"{}\"", pack.name));
+        }
+        packs.retain(|p| p.name != pack.name);
+    }
+    packs.push(pack);
+    save_packs(packs);
+    Ok(())
+}
+
+pub fn remove(packs: &mut Vec<EmojiPack>, name: &str) {
+    packs.retain(|p| p.name != name);
+    save_packs(packs);
+}
+
+/// Image for `:name:`, searching packs in install order.
+pub fn lookup<'a>(packs: &'a [EmojiPack], name: &str) -> Option<&'a str> {
+    packs
+        .iter()
+        .find_map(|p| p.emoji.get(name))
+        .map(String::as_str)
+}
+
+/// Splits `text` around `:name:` sequences that match a custom emoji.
+pub fn segments<'a>(text: &'a str, packs: &'a [EmojiPack]) -> Vec<Segment<'a>> {
+    let mut segments = vec![];
+    let mut text_start = 0;
+    let mut cursor = 0;
+
+    while let Some(open) = text[cursor..].find(':').map(|i| cursor + i) {
+        let close = match text[open + 1..].find(':') {
+            Some(i) => open + 1 + i,
+            None => break,
+        };
+        let name = &text[open + 1..close];
+        match lookup(packs, name) {
+            Some(url) => {
+                if open > text_start {
+                    segments.push(Segment::Text(&text[text_start..open]));
+                }
+                segments.push(Segment::Emoji { name, url });
+                cursor = close + 1;
+                text_start = cursor;
+            }
+            // The closing colon may open the next shortcode, as in `a:b:partyparrot:`.
+            None => cursor = close,
+        }
+    }
+
+    if text_start < text.len() {
+        segments.push(Segment::Text(&text[text_start..]));
+    }
+    segments
+}
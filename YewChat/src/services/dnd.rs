@@ -0,0 +1,92 @@
+use serde::{Deserialize, Serialize};
+
+pub const DAY_LABELS: [&str; 7] = ["S", "M", "T", "W", "T", "F", "S"];
+
+/// A recurring quiet period in local time. `end` before `start` runs past
+/// midnight, e.g. 19:00–08:00.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Schedule {
+    pub enabled: bool,
+    /// Days the period starts on, Sunday first.
+    pub days: [bool; 7],
+    /// Minutes after midnight.
+    pub start: u32,
+    pub end: u32,
+}
+
+impl Default for Schedule {
+    /// Weeknights, 19:00 to 08:00.
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            days: [false, true, true, true, true, true, false],
+            start: 19 * 60,
+            end: 8 * 60,
+        }
+    }
+}
+
+impl Schedule {
+    /// Whether the period covers `minute` past midnight on `day` (0 is Sunday).
+    pub fn covers(&self, day: usize, minute: u32) -> bool {
+        if !self.enabled {
+            return false;
+        }
+        let previous = (day + 6) % 7;
+        if self.start < self.end {
+            self.days[day] && (self.start..self.end).contains(&minute)
+        } else {
+            (self.days[day] && minute >= self.start) || (self.days[previous] && minute < self.end)
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DndSettings {
+    /// Turned on by hand, until turned off again.
+    pub manual: bool,
+    pub schedule: Schedule,
+    pub allow_direct: bool,
+    pub allow_users: Vec<String>,
+}
+
+impl Default for DndSettings {
+    fn default() -> Self {
+        Self {
+            manual: false,
+            schedule: Schedule::default(),
+            allow_direct: true,
+            allow_users: vec![],
+        }
+    }
+}
+
+impl DndSettings {
+    /// Whether do not disturb is on right now, by hand or by schedule.
+    pub fn is_active(&self) -> bool {
+        if self.manual {
+            return true;
+        }
+        let now = js_sys::Date::new_0();
+        self.schedule
+            .covers(now.get_day() as usize, now.get_hours() * 60 + now.get_minutes())
+    }
+
+    /// Whether a message from `from` gets through while do not disturb is on.
+    pub fn allows(&self, from: &str, direct: bool) -> bool {
+        (direct && self.allow_direct) || self.allow_users.iter().any(|name| name == from)
+    }
+}
+
+/// Formats minutes after midnight as `HH:MM`, as time inputs expect.
+pub fn format_time(minutes: u32) -> String {
+    format!("{:02}:{:02}", minutes / 60, minutes % 60)
+}
+
+pub fn parse_time(value: &str) -> Option<u32> {
+    let (hours, minutes) = value.split_once(':')?;
+    let (hours, minutes): (u32, u32) = (hours.parse().ok()?, minutes.get(..2)?.parse().ok()?);
+    (hours < 24 && minutes < 60).then(|| hours * 60 + minutes)
+}
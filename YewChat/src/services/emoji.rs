@@ -0,0 +1,178 @@
+use emojis::{Emoji, Group};
+use gloo::storage::{LocalStorage, Storage};
+use serde::{Deserialize, Serialize};
+
+const RECENTS_KEY: &str = "yewchat.emoji.recent";
+const MAX_RECENTS: usize = 24;
+const MAX_SUGGESTIONS: usize = 8;
+
+/// Picker categories in display order, with a representative icon.
+/// The Unicode "Component" group (bare skin tones, hair styles) is left out.
+pub const CATEGORIES: [(Group, &str, &str); 9] = [
+    (Group::SmileysAndEmotion, "Smileys & Emotion", "😀"),
+    (Group::PeopleAndBody, "People & Body", "👋"),
+    (Group::AnimalsAndNature, "Animals & Nature", "🐻"),
+    (Group::FoodAndDrink, "Food & Drink", "🍔"),
+    (Group::TravelAndPlaces, "Travel & Places", "✈️"),
+    (Group::Activities, "Activities", "⚽"),
+    (Group::Objects, "Objects", "💡"),
+    (Group::Symbols, "Symbols", "❤️"),
+    (Group::Flags, "Flags", "🏁"),
+];
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum SkinTone {
+    #[default]
+    Default,
+    Light,
+    MediumLight,
+    Medium,
+    MediumDark,
+    Dark,
+}
+
+impl SkinTone {
+    pub const ALL: [SkinTone; 6] = [
+        SkinTone::Default,
+        SkinTone::Light,
+        SkinTone::MediumLight,
+        SkinTone::Medium,
+        SkinTone::MediumDark,
+        SkinTone::Dark,
+    ];
+
+    pub fn swatch(&self) -> &'static str {
+        match self {
+            SkinTone::Default => "✋",
+            SkinTone::Light => "✋🏻",
+            SkinTone::MediumLight => "✋🏼",
+            SkinTone::Medium => "✋🏽",
+            SkinTone::MediumDark => "✋🏾",
+            SkinTone::Dark => "✋🏿",
+        }
+    }
+
+    fn to_emojis(self) -> emojis::SkinTone {
+        match self {
+            SkinTone::Default => emojis::SkinTone::Default,
+            SkinTone::Light => emojis::SkinTone::Light,
+            SkinTone::MediumLight => emojis::SkinTone::MediumLight,
+            SkinTone::Medium => emojis::SkinTone::Medium,
+            SkinTone::MediumDark => emojis::SkinTone::MediumDark,
+            SkinTone::Dark => emojis::SkinTone::Dark,
+        }
+    }
+}
+
+/// The emoji as it should be inserted, with `tone` applied when supported.
+pub fn with_tone(emoji: &'static Emoji, tone: SkinTone) -> &'static str {
+    emoji
+        .with_skin_tone(tone.to_emojis())
+        .unwrap_or(emoji)
+        .as_str()
+}
+
+pub fn in_category(group: Group) -> Vec<&'static Emoji> {
+    emojis::iter().filter(|e| e.group() == group).collect()
+}
+
+/// Emojis whose name or any shortcode contains every word of `query`.
+pub fn search(query: &str) -> Vec<&'static Emoji> {
+    let words: Vec<String> = query
+        .split_whitespace()
+        .map(|w| w.trim_matches(':').to_lowercase())
+        .filter(|w| !w.is_empty())
+        .collect();
+    if words.is_empty() {
+        return vec![];
+    }
+
+    emojis::iter()
+        .filter(|e| {
+            let haystack = format!("{} {}", e.name(), e.shortcodes().collect::<Vec<_>>().join(" "));
+            words.iter().all(|w| haystack.contains(w.as_str()))
+        })
+        .collect()
+}
+
+/// The partial shortcode being typed at the end of `text`, e.g. `"smi"` for
+/// `"hello :smi"`. Needs at least two characters after the colon.
+pub fn shortcode_query(text: &str) -> Option<&str> {
+    let token = text.rsplit(char::is_whitespace).next()?;
+    let query = token.strip_prefix(':')?;
+    let valid = query.len() >= 2
+        && query
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '+' || c == '-');
+    if valid {
+        Some(query)
+    } else {
+        None
+    }
+}
+
+/// Shortcodes starting with `prefix`, shortest first so `:smi` offers
+/// `:smile:` before `:smiley:`.
+pub fn shortcode_suggestions(prefix: &str) -> Vec<(&'static str, &'static Emoji)> {
+    let prefix = prefix.to_lowercase();
+    let mut matches: Vec<(&'static str, &'static Emoji)> = emojis::iter()
+        .flat_map(|e| e.shortcodes().map(move |code| (code, e)))
+        .filter(|(code, _)| code.starts_with(&prefix))
+        .collect();
+    matches.sort_by_key(|(code, _)| (code.len(), *code));
+    matches.truncate(MAX_SUGGESTIONS);
+    matches
+}
+
+pub fn complete_shortcode(text: &str, emoji: &str) -> String {
+    match shortcode_query(text) {
+        Some(query) => {
+            let start = text.len() - query.len() - 1;
+            format!("{}{} ", &text[..start], emoji)
+        }
+        None => text.to_string(),
+    }
+}
+
+/// Expands complete `:shortcode:` sequences into emojis, leaving unknown
+/// ones untouched.
+pub fn replace_shortcodes(text: &str, tone: SkinTone) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find(':') {
+        out.push_str(&rest[..start]);
+        let after = &rest[start + 1..];
+        let emoji = after
+            .find(':')
+            .map(|end| (end, &after[..end]))
+            .filter(|(_, code)| !code.is_empty() && !code.contains(char::is_whitespace))
+            .and_then(|(end, code)| emojis::get_by_shortcode(code).map(|e| (end, e)));
+        match emoji {
+            Some((end, emoji)) => {
+                out.push_str(with_tone(emoji, tone));
+                rest = &after[end + 1..];
+            }
+            None => {
+                out.push(':');
+                rest = after;
+            }
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+/// Recently used emojis, most recent first, kept in local storage.
+pub fn load_recents() -> Vec<String> {
+    LocalStorage::get(RECENTS_KEY).unwrap_or_default()
+}
+
+pub fn push_recent(recents: &mut Vec<String>, emoji: &str) {
+    recents.retain(|e| e != emoji);
+    recents.insert(0, emoji.to_string());
+    recents.truncate(MAX_RECENTS);
+    if let Err(e) = LocalStorage::set(RECENTS_KEY, &*recents) {
+        log::error!("failed to save recent emojis: {:?}", e);
+    }
+}
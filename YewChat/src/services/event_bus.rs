@@ -5,6 +5,15 @@ use yew_agent::{Agent, AgentLink, Context, HandlerId};
 #[derive(Serialize, Deserialize, Debug)]
 pub enum Request {
     EventBusMsg(String),
+    Bytes(Vec<u8>),
+    Closed,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub enum BusMessage {
+    Text(String),
+    Bytes(Vec<u8>),
+    Closed,
 }
 
 pub struct EventBus {
@@ -16,7 +25,7 @@ impl Agent for EventBus {
     type Reach = Context<Self>;
     type Message = ();
     type Input = Request;
-    type Output = String;
+    type Output = BusMessage;
 
     fn create(link: AgentLink<Self>) -> Self {
         Self {
@@ -28,12 +37,13 @@ impl Agent for EventBus {
     fn update(&mut self, _msg: Self::Message) {}
 
     fn handle_input(&mut self, msg: Self::Input, _id: HandlerId) {
-        match msg {
-            Request::EventBusMsg(s) => {
-                for sub in self.subscribers.iter() {
-                    self.link.respond(*sub, s.clone())
-                }
-            }
+        let output = match msg {
+            Request::EventBusMsg(s) => BusMessage::Text(s),
+            Request::Bytes(b) => BusMessage::Bytes(b),
+            Request::Closed => BusMessage::Closed,
+        };
+        for sub in self.subscribers.iter() {
+            self.link.respond(*sub, output.clone())
         }
     }
 
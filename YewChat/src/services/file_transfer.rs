@@ -0,0 +1,540 @@
+use std::cell::Cell;
+use std::rc::Rc;
+
+use gloo::file::Blob;
+use serde::{Deserialize, Serialize};
+
+use crate::services::object_url::ObjectUrl;
+
+pub const MAX_FILE_SIZE: u64 = 25 * 1024 * 1024;
+pub const CHUNK_SIZE: usize = 64 * 1024;
+
+/// Prefix of every binary chunk frame, so file data can be told apart from
+/// text that happens to arrive as `Message::Bytes`.
+const MAGIC: &[u8; 4] = b"YCF1";
+const HEADER_LEN: usize = MAGIC.len() + 8 + 4;
+
+/// Announces a file before its chunks are streamed.
+///
+/// Sent as JSON in a `file` message; the server fills in `from`.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FileOffer {
+    pub id: u64,
+    pub name: String,
+    pub size: u64,
+    pub mime: String,
+    pub total_chunks: u32,
+    pub checksum: u32,
+    #[serde(default)]
+    pub from: String,
+}
+
+/// Control message for an in-flight transfer, sent as JSON in a
+/// `filecontrol` message.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "action", rename_all = "camelCase")]
+pub enum FileControl {
+    /// The sender gave up; receivers should drop what they have.
+    #[serde(rename_all = "camelCase")]
+    Cancel { id: u64 },
+    /// A receiver is missing chunks from `from_index` onwards.
+    #[serde(rename_all = "camelCase")]
+    Resume { id: u64, from_index: u32 },
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum TransferState {
+    InProgress,
+    Paused,
+    Complete,
+    Cancelled,
+    Failed(String),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Direction {
+    Outgoing,
+    Incoming,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct TransferView {
+    pub offer: FileOffer,
+    pub direction: Direction,
+    pub done: u32,
+    pub state: TransferState,
+    pub url: Option<String>,
+}
+
+impl TransferView {
+    pub fn progress(&self) -> f64 {
+        if self.offer.total_chunks == 0 {
+            1.0
+        } else {
+            self.done as f64 / self.offer.total_chunks as f64
+        }
+    }
+}
+
+pub struct OutgoingTransfer {
+    pub offer: FileOffer,
+    pub data: Rc<Vec<u8>>,
+    acknowledged: Vec<bool>,
+    sent: u32,
+    pub state: TransferState,
+    pub cursor: Rc<StreamCursor>,
+}
+
+impl OutgoingTransfer {
+    pub fn new(name: String, mime: String, data: Vec<u8>) -> Result<Self, String> {
+        if data.len() as u64 > MAX_FILE_SIZE {
+            return Err(format!(
+                "{} is {}, the limit is {}",
+                name,
+                format_size(data.len() as u64),
+                format_size(MAX_FILE_SIZE)
+            ));
+        }
+
+        let offer = FileOffer {
+            id: random_id(),
+            name,
+            size: data.len() as u64,
+            mime: if mime.is_empty() {
+                "application/octet-stream".into()
+            } else {
+                mime
+            },
+            total_chunks: data.len().div_ceil(CHUNK_SIZE) as u32,
+            checksum: crc32(&data),
+            from: String::new(),
+        };
+
+        let state = if offer.total_chunks == 0 {
+            TransferState::Complete
+        } else {
+            TransferState::InProgress
+        };
+        Ok(Self {
+            acknowledged: vec![false; offer.total_chunks as usize],
+            offer,
+            data: Rc::new(data),
+            sent: 0,
+            state,
+            cursor: Rc::new(StreamCursor::default()),
+        })
+    }
+
+    /// Records that the server relayed chunk `index`, finishing the
+    /// transfer once every chunk has been.
+    pub fn acknowledge(&mut self, index: u32) {
+        if let Some(acked) = self.acknowledged.get_mut(index as usize) {
+            if !*acked {
+                *acked = true;
+                self.sent += 1;
+            }
+        }
+        if self.sent == self.offer.total_chunks && self.state == TransferState::InProgress {
+            self.state = TransferState::Complete;
+        }
+    }
+
+    pub fn first_unacknowledged(&self) -> u32 {
+        self.acknowledged
+            .iter()
+            .position(|acked| !acked)
+            .unwrap_or(self.acknowledged.len()) as u32
+    }
+
+    pub fn cancel(&mut self) {
+        self.cursor.cancel();
+        self.state = TransferState::Cancelled;
+    }
+
+    pub fn view(&self) -> TransferView {
+        TransferView {
+            offer: self.offer.clone(),
+            direction: Direction::Outgoing,
+            done: self.sent,
+            state: self.state.clone(),
+            url: None,
+        }
+    }
+}
+
+/// Position of the task streaming an outgoing file, shared with it so a
+/// resume request rewinds the running stream instead of starting another.
+#[derive(Default)]
+pub struct StreamCursor {
+    next: Cell<u32>,
+    running: Cell<bool>,
+    /// Bumped to stop the running stream, e.g. when its socket closed.
+    generation: Cell<u32>,
+    cancelled: Cell<bool>,
+}
+
+impl StreamCursor {
+    /// Moves the stream back to `index`. Returns the generation to stream
+    /// under when no stream is running and a new one has to be spawned.
+    pub fn start(&self, index: u32) -> Option<u32> {
+        if self.cancelled.get() {
+            return None;
+        }
+        if self.running.get() {
+            self.next.set(self.next.get().min(index));
+            return None;
+        }
+        self.next.set(index);
+        self.running.set(true);
+        Some(self.generation.get())
+    }
+
+    /// The next chunk for the stream of `generation` to send, or `None`
+    /// once it should stop.
+    pub fn advance(&self, generation: u32, total: u32) -> Option<u32> {
+        if self.cancelled.get() || self.generation.get() != generation {
+            return None;
+        }
+        let index = self.next.get();
+        if index >= total {
+            self.running.set(false);
+            return None;
+        }
+        self.next.set(index + 1);
+        Some(index)
+    }
+
+    pub fn stop(&self) {
+        self.generation.set(self.generation.get().wrapping_add(1));
+        self.running.set(false);
+    }
+
+    fn cancel(&self) {
+        self.cancelled.set(true);
+        self.stop();
+    }
+}
+
+pub struct IncomingTransfer {
+    pub offer: FileOffer,
+    chunks: Vec<Option<Vec<u8>>>,
+    received: u32,
+    pub state: TransferState,
+    url: Option<ObjectUrl>,
+}
+
+impl IncomingTransfer {
+    pub fn new(offer: FileOffer) -> Self {
+        let state = if offer.size > MAX_FILE_SIZE {
+            TransferState::Failed(format!(
+                "File exceeds the {} limit",
+                format_size(MAX_FILE_SIZE)
+            ))
+        } else if offer.total_chunks as u64 != offer.size.div_ceil(CHUNK_SIZE as u64) {
+            TransferState::Failed("The file's chunk count doesn't match its size".into())
+        } else {
+            TransferState::InProgress
+        };
+        let chunks = match state {
+            TransferState::InProgress => vec![None; offer.total_chunks as usize],
+            _ => vec![],
+        };
+
+        let mut transfer = Self {
+            offer,
+            chunks,
+            received: 0,
+            state,
+            url: None,
+        };
+        if transfer.state == TransferState::InProgress && transfer.offer.total_chunks == 0 {
+            transfer.finish();
+        }
+        transfer
+    }
+
+    /// Stores one chunk, finishing the transfer once the last one arrives.
+    /// Duplicates from a resumed send are ignored.
+    pub fn accept_chunk(&mut self, index: u32, payload: &[u8]) {
+        if self.state != TransferState::InProgress && self.state != TransferState::Paused {
+            return;
+        }
+        if index >= self.offer.total_chunks {
+            return;
+        }
+        // Every chunk but the last is full, so nothing bigger than the offer is ever held.
+        let start = index as u64 * CHUNK_SIZE as u64;
+        if payload.len() as u64 != (self.offer.size - start).min(CHUNK_SIZE as u64) {
+            self.chunks.clear();
+            self.state = TransferState::Failed("Received a malformed chunk".into());
+            return;
+        }
+        let slot = match self.chunks.get_mut(index as usize) {
+            Some(slot) => slot,
+            None => return,
+        };
+        if slot.is_none() {
+            *slot = Some(payload.to_vec());
+            self.received += 1;
+        }
+        self.state = TransferState::InProgress;
+
+        if self.received == self.offer.total_chunks {
+            self.finish();
+        }
+    }
+
+    pub fn first_missing(&self) -> u32 {
+        self.chunks
+            .iter()
+            .position(Option::is_none)
+            .unwrap_or(self.chunks.len()) as u32
+    }
+
+    pub fn is_pending(&self) -> bool {
+        matches!(
+            self.state,
+            TransferState::InProgress | TransferState::Paused
+        )
+    }
+
+    pub fn pause(&mut self) {
+        if self.state == TransferState::InProgress {
+            self.state = TransferState::Paused;
+        }
+    }
+
+    pub fn cancel(&mut self) {
+        if self.is_pending() {
+            self.chunks.clear();
+            self.state = TransferState::Cancelled;
+        }
+    }
+
+    fn finish(&mut self) {
+        let data: Vec<u8> = self.chunks.drain(..).flatten().flatten().collect();
+        if crc32(&data) != self.offer.checksum || data.len() as u64 != self.offer.size {
+            self.state = TransferState::Failed("Checksum mismatch".into());
+            return;
+        }
+        let blob = Blob::new_with_options(data.as_slice(), Some(&self.offer.mime));
+        self.url = Some(ObjectUrl::from(blob));
+        self.state = TransferState::Complete;
+    }
+
+    pub fn view(&self) -> TransferView {
+        TransferView {
+            offer: self.offer.clone(),
+            direction: Direction::Incoming,
+            done: self.received,
+            state: self.state.clone(),
+            url: self.url.as_ref().map(|url| url.to_string()),
+        }
+    }
+}
+
+pub fn chunk_frame(id: u64, data: &[u8], index: u32) -> Vec<u8> {
+    let start = (index as usize * CHUNK_SIZE).min(data.len());
+    let end = (start + CHUNK_SIZE).min(data.len());
+    encode_chunk(id, index, &data[start..end])
+}
+
+pub fn is_chunk_frame(frame: &[u8]) -> bool {
+    frame.len() >= HEADER_LEN && frame.starts_with(MAGIC)
+}
+
+/// Frame layout: `YCF1`, transfer id (u64, big endian), chunk index (u32,
+/// big endian), then the payload.
+pub fn encode_chunk(id: u64, index: u32, payload: &[u8]) -> Vec<u8> {
+    let mut frame = Vec::with_capacity(HEADER_LEN + payload.len());
+    frame.extend_from_slice(MAGIC);
+    frame.extend_from_slice(&id.to_be_bytes());
+    frame.extend_from_slice(&index.to_be_bytes());
+    frame.extend_from_slice(payload);
+    frame
+}
+
+pub fn decode_chunk(frame: &[u8]) -> Option<(u64, u32, &[u8])> {
+    if !is_chunk_frame(frame) {
+        return None;
+    }
+    let mut id = [0u8; 8];
+    id.copy_from_slice(&frame[4..12]);
+    let mut index = [0u8; 4];
+    index.copy_from_slice(&frame[12..16]);
+    Some((
+        u64::from_be_bytes(id),
+        u32::from_be_bytes(index),
+        &frame[HEADER_LEN..],
+    ))
+}
+
+/// CRC-32 (IEEE), the same checksum used by zip and PNG.
+pub fn crc32(data: &[u8]) -> u32 {
+    let mut crc = 0xFFFF_FFFFu32;
+    for byte in data {
+        crc ^= *byte as u32;
+        for _ in 0..8 {
+            let mask = (crc & 1).wrapping_neg();
+            crc = (crc >> 1) ^ (0xEDB8_8320 & mask);
+        }
+    }
+    !crc
+}
+
+pub fn format_size(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["B", "KB", "MB", "GB"];
+    let mut size = bytes as f64;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{} {}", bytes, UNITS[0])
+    } else {
+        format!("{:.1} {}", size, UNITS[unit])
+    }
+}
+
+/// Random id that survives a round trip through JavaScript numbers.
+fn random_id() -> u64 {
+    (js_sys::Math::random() * 9_007_199_254_740_992.0) as u64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn offer(size: u64) -> FileOffer {
+        FileOffer {
+            id: 7,
+            name: "notes.txt".into(),
+            size,
+            mime: "text/plain".into(),
+            total_chunks: size.div_ceil(CHUNK_SIZE as u64) as u32,
+            checksum: 0,
+            from: "alice".into(),
+        }
+    }
+
+    #[test]
+    fn crc32_matches_the_check_value() {
+        assert_eq!(crc32(b"123456789"), 0xCBF4_3926);
+        assert_eq!(crc32(b""), 0);
+    }
+
+    #[test]
+    fn chunks_round_trip() {
+        let frame = encode_chunk(42, 3, b"payload");
+        assert!(is_chunk_frame(&frame));
+        assert_eq!(decode_chunk(&frame), Some((42, 3, &b"payload"[..])));
+        assert_eq!(decode_chunk(&encode_chunk(1, 0, b"")), Some((1, 0, &b""[..])));
+    }
+
+    #[test]
+    fn short_or_foreign_frames_are_not_chunks() {
+        let frame = encode_chunk(42, 3, b"");
+        assert_eq!(decode_chunk(&frame[..HEADER_LEN - 1]), None);
+        assert_eq!(decode_chunk(b"YCF0aaaaaaaaaaaaaaaa"), None);
+    }
+
+    #[test]
+    fn chunk_frame_slices_the_file() {
+        let data = vec![1u8; CHUNK_SIZE + 10];
+        assert_eq!(decode_chunk(&chunk_frame(9, &data, 0)).unwrap().2.len(), CHUNK_SIZE);
+        assert_eq!(decode_chunk(&chunk_frame(9, &data, 1)).unwrap().2.len(), 10);
+    }
+
+    #[test]
+    fn start_while_running_rewinds_instead_of_spawning() {
+        let cursor = StreamCursor::default();
+        let generation = cursor.start(0).unwrap();
+        assert_eq!(cursor.advance(generation, 10), Some(0));
+        assert_eq!(cursor.advance(generation, 10), Some(1));
+        assert_eq!(cursor.advance(generation, 10), Some(2));
+        assert_eq!(cursor.start(1), None);
+        assert_eq!(cursor.advance(generation, 10), Some(1));
+        // Only ever moves back, never past chunks still to send.
+        assert_eq!(cursor.start(8), None);
+        assert_eq!(cursor.advance(generation, 10), Some(2));
+    }
+
+    #[test]
+    fn stream_finishes_at_the_last_chunk() {
+        let cursor = StreamCursor::default();
+        let generation = cursor.start(1).unwrap();
+        assert_eq!(cursor.advance(generation, 2), Some(1));
+        assert_eq!(cursor.advance(generation, 2), None);
+        assert!(cursor.start(0).is_some());
+    }
+
+    #[test]
+    fn stop_ends_the_old_stream() {
+        let cursor = StreamCursor::default();
+        let old = cursor.start(0).unwrap();
+        cursor.stop();
+        assert_eq!(cursor.advance(old, 10), None);
+        let new = cursor.start(4).unwrap();
+        assert_ne!(new, old);
+        assert_eq!(cursor.advance(new, 10), Some(4));
+    }
+
+    #[test]
+    fn cancel_stops_for_good() {
+        let cursor = StreamCursor::default();
+        let generation = cursor.start(0).unwrap();
+        cursor.cancel();
+        assert_eq!(cursor.advance(generation, 10), None);
+        assert_eq!(cursor.start(0), None);
+    }
+
+    #[test]
+    fn offers_must_match_their_size() {
+        let mut bogus = offer(1);
+        bogus.total_chunks = 4_000_000_000;
+        assert!(matches!(IncomingTransfer::new(bogus).state, TransferState::Failed(_)));
+        let too_big = offer(MAX_FILE_SIZE + 1);
+        assert!(matches!(IncomingTransfer::new(too_big).state, TransferState::Failed(_)));
+        assert_eq!(IncomingTransfer::new(offer(10)).state, TransferState::InProgress);
+    }
+
+    #[test]
+    fn duplicate_and_out_of_range_chunks_are_ignored() {
+        let mut transfer = IncomingTransfer::new(offer(3 * CHUNK_SIZE as u64));
+        let chunk = vec![0u8; CHUNK_SIZE];
+        transfer.accept_chunk(1, &chunk);
+        transfer.accept_chunk(1, &chunk);
+        transfer.accept_chunk(3, &chunk);
+        transfer.accept_chunk(u32::MAX, &chunk);
+        assert_eq!(transfer.view().done, 1);
+        assert_eq!(transfer.first_missing(), 0);
+        assert_eq!(transfer.state, TransferState::InProgress);
+    }
+
+    #[test]
+    fn oversized_or_short_chunks_fail_the_transfer() {
+        let mut transfer = IncomingTransfer::new(offer(2 * CHUNK_SIZE as u64));
+        transfer.accept_chunk(0, &vec![0u8; CHUNK_SIZE + 1]);
+        assert!(matches!(transfer.state, TransferState::Failed(_)));
+
+        let mut transfer = IncomingTransfer::new(offer(2 * CHUNK_SIZE as u64));
+        transfer.accept_chunk(0, &[0u8; 10]);
+        assert!(matches!(transfer.state, TransferState::Failed(_)));
+
+        let mut transfer = IncomingTransfer::new(offer(CHUNK_SIZE as u64 + 10));
+        transfer.accept_chunk(1, &[0u8; 11]);
+        assert!(matches!(transfer.state, TransferState::Failed(_)));
+    }
+
+    #[test]
+    fn sizes_are_human_readable() {
+        assert_eq!(format_size(0), "0 B");
+        assert_eq!(format_size(1023), "1023 B");
+        assert_eq!(format_size(1024), "1.0 KB");
+        assert_eq!(format_size(1536), "1.5 KB");
+        assert_eq!(format_size(MAX_FILE_SIZE), "25.0 MB");
+        assert_eq!(format_size(5 * 1024 * 1024 * 1024 * 1024), "5120.0 GB");
+    }
+}
@@ -0,0 +1,102 @@
+use web_sys::TouchEvent;
+
+/// Minimum horizontal travel, in pixels, for a touch to count as a swipe.
+const SWIPE_MIN_PX: i32 = 60;
+/// Touches starting this close to the left edge may pull a drawer open.
+const EDGE_PX: i32 = 24;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Swipe {
+    Left,
+    Right {
+        /// Whether the touch began at the left edge of the screen.
+        from_edge: bool,
+    },
+}
+
+/// Recognises horizontal swipes from a touch's start and end points.
+#[derive(Default)]
+pub struct SwipeTracker {
+    start: Option<(i32, i32)>,
+}
+
+impl SwipeTracker {
+    pub fn start(&mut self, point: (i32, i32)) {
+        self.start = Some(point);
+    }
+
+    /// Finishes the touch, returning the swipe it made, if any. Mostly
+    /// vertical movement is scrolling, not a swipe.
+    pub fn end(&mut self, (x, y): (i32, i32)) -> Option<Swipe> {
+        let (start_x, start_y) = self.start.take()?;
+        let (dx, dy) = (x - start_x, y - start_y);
+        if dx.abs() < SWIPE_MIN_PX || dx.abs() < dy.abs() * 2 {
+            return None;
+        }
+        Some(if dx < 0 {
+            Swipe::Left
+        } else {
+            Swipe::Right {
+                from_edge: start_x <= EDGE_PX,
+            }
+        })
+    }
+}
+
+/// Where the first finger of a touch event is. `changed` reads the finger
+/// that just lifted, which `touchend` no longer lists as touching.
+pub fn touch_point(e: &TouchEvent, changed: bool) -> Option<(i32, i32)> {
+    let touches = if changed {
+        e.changed_touches()
+    } else {
+        e.touches()
+    };
+    touches.get(0).map(|t| (t.client_x(), t.client_y()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn swipe(from: (i32, i32), to: (i32, i32)) -> Option<Swipe> {
+        let mut tracker = SwipeTracker::default();
+        tracker.start(from);
+        tracker.end(to)
+    }
+
+    #[test]
+    fn short_moves_are_taps() {
+        assert_eq!(swipe((100, 100), (150, 100)), None);
+    }
+
+    #[test]
+    fn mostly_vertical_moves_are_scrolls() {
+        assert_eq!(swipe((100, 100), (180, 150)), None);
+    }
+
+    #[test]
+    fn left_swipe() {
+        assert_eq!(swipe((200, 100), (100, 110)), Some(Swipe::Left));
+    }
+
+    #[test]
+    fn right_swipe_remembers_the_edge() {
+        assert_eq!(
+            swipe((10, 100), (120, 100)),
+            Some(Swipe::Right { from_edge: true })
+        );
+        assert_eq!(
+            swipe((100, 100), (200, 100)),
+            Some(Swipe::Right { from_edge: false })
+        );
+    }
+
+    #[test]
+    fn end_without_start_is_nothing() {
+        let mut tracker = SwipeTracker::default();
+        assert_eq!(tracker.end((200, 100)), None);
+        tracker.start((200, 100));
+        assert_eq!(tracker.end((50, 100)), Some(Swipe::Left));
+        assert_eq!(tracker.end((50, 100)), None);
+    }
+}
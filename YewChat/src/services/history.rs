@@ -0,0 +1,82 @@
+use std::collections::HashMap;
+
+use gloo::storage::{LocalStorage, Storage};
+use serde::{Deserialize, Serialize};
+
+const STORAGE_KEY: &str = "yewchat.history";
+/// Most messages kept; the oldest are dropped first.
+const MAX_ENTRIES: usize = 500;
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StoredMessage {
+    pub from: String,
+    #[serde(default)]
+    pub to: Option<String>,
+    pub message: String,
+    #[serde(default)]
+    pub emote: bool,
+    pub time: f64,
+}
+
+/// Recent messages received on this device, persisted in local storage.
+///
+/// Inline images are sent as data URLs and would quickly fill the storage
+/// quota, so they are left out.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct MessageHistory {
+    messages: Vec<StoredMessage>,
+}
+
+impl MessageHistory {
+    pub fn load() -> Self {
+        Self {
+            messages: LocalStorage::get(STORAGE_KEY).unwrap_or_default(),
+        }
+    }
+
+    pub fn save(&self) {
+        if let Err(e) = LocalStorage::set(STORAGE_KEY, &self.messages) {
+            log::error!("failed to save message history: {:?}", e);
+        }
+    }
+
+    pub fn push(&mut self, message: StoredMessage) {
+        if message.message.starts_with("data:") {
+            return;
+        }
+        self.messages.push(message);
+        if self.messages.len() > MAX_ENTRIES {
+            let excess = self.messages.len() - MAX_ENTRIES;
+            self.messages.drain(..excess);
+        }
+    }
+
+    /// Attributes everything `from` wrote, or was sent, to `to` instead.
+    pub fn rename(&mut self, from: &str, to: &str) {
+        for message in &mut self.messages {
+            if message.from == from {
+                message.from = to.to_string();
+            }
+            if message.to.as_deref() == Some(from) {
+                message.to = Some(to.to_string());
+            }
+        }
+    }
+
+    pub fn by<'a>(&'a self, name: &'a str) -> impl Iterator<Item = &'a StoredMessage> + 'a {
+        self.messages.iter().filter(move |m| m.from == name)
+    }
+
+    pub fn count_by(&self, name: &str) -> usize {
+        self.by(name).count()
+    }
+
+    pub fn last_active(&self) -> HashMap<&str, f64> {
+        let mut last = HashMap::new();
+        for message in &self.messages {
+            last.insert(message.from.as_str(), message.time);
+        }
+        last
+    }
+}
@@ -0,0 +1,253 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use futures::channel::oneshot;
+use gloo::events::EventListener;
+use gloo::file::File;
+use wasm_bindgen::{JsCast, JsValue};
+use web_sys::{CanvasRenderingContext2d, HtmlCanvasElement, HtmlImageElement};
+
+use crate::services::object_url::ObjectUrl;
+
+/// Largest data URL an attached image may produce, in bytes.
+pub const MAX_IMAGE_BYTES: usize = 512 * 1024;
+/// Longest edge an attached image is scaled down to before encoding.
+const MAX_DIMENSION: f64 = 1920.0;
+const QUALITY_STEPS: [f64; 4] = [0.85, 0.7, 0.55, 0.4];
+const MIN_DIMENSION: f64 = 64.0;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct PreparedImage {
+    pub name: String,
+    pub data_url: String,
+}
+
+pub fn is_image(file: &File) -> bool {
+    file.raw_mime_type().starts_with("image/")
+}
+
+/// Re-encodes an image through a canvas so it fits in `MAX_IMAGE_BYTES`.
+///
+/// Drawing to a canvas keeps only the pixels, which also strips EXIF data
+/// such as GPS coordinates. GIFs small enough to fit are sent without their
+/// comment and metadata blocks but otherwise untouched, so they keep their
+/// animation.
+pub async fn prepare_image(file: File) -> Result<PreparedImage, String> {
+    let name = file.name();
+
+    if file.raw_mime_type() == "image/gif" && file.size() as usize <= MAX_IMAGE_BYTES {
+        let bytes = gloo::file::futures::read_as_bytes(&file)
+            .await
+            .map_err(|e| format!("Could not read {}: {}", name, e))?;
+        if let Some(gif) = strip_gif_metadata(&bytes) {
+            let data_url = format!("data:image/gif;base64,{}", base64(&gif));
+            if data_url.len() <= MAX_IMAGE_BYTES {
+                return Ok(PreparedImage { name, data_url });
+            }
+        }
+    }
+
+    let url = ObjectUrl::from(file);
+    let image = load_image(&url)
+        .await
+        .map_err(|_| format!("{} is not a supported image", name))?;
+
+    let (width, height) = (image.natural_width() as f64, image.natural_height() as f64);
+    let mut scale = (MAX_DIMENSION / width.max(height)).min(1.0);
+
+    while width.max(height) * scale >= MIN_DIMENSION {
+        for quality in QUALITY_STEPS {
+            let data_url = encode_jpeg(&image, width * scale, height * scale, quality)
+                .map_err(|e| format!("Could not encode {}: {:?}", name, e))?;
+            if data_url.len() <= MAX_IMAGE_BYTES {
+                return Ok(PreparedImage { name, data_url });
+            }
+        }
+        scale *= 0.75;
+    }
+
+    Err(format!("{} is too large to attach", name))
+}
+
+async fn load_image(src: &str) -> Result<HtmlImageElement, JsValue> {
+    let image = HtmlImageElement::new()?;
+    let (tx, rx) = oneshot::channel::<bool>();
+    let tx = Rc::new(RefCell::new(Some(tx)));
+
+    let _on_load = {
+        let tx = tx.clone();
+        EventListener::once(&image, "load", move |_| {
+            if let Some(tx) = tx.borrow_mut().take() {
+                let _ = tx.send(true);
+            }
+        })
+    };
+    let _on_error = EventListener::once(&image, "error", move |_| {
+        if let Some(tx) = tx.borrow_mut().take() {
+            let _ = tx.send(false);
+        }
+    });
+
+    image.set_src(src);
+    match rx.await {
+        Ok(true) => Ok(image),
+        _ => Err(JsValue::from_str("image failed to load")),
+    }
+}
+
+fn encode_jpeg(
+    image: &HtmlImageElement,
+    width: f64,
+    height: f64,
+    quality: f64,
+) -> Result<String, JsValue> {
+    let document = web_sys::window()
+        .and_then(|w| w.document())
+        .ok_or_else(|| JsValue::from_str("no document"))?;
+    let canvas: HtmlCanvasElement = document.create_element("canvas")?.dyn_into()?;
+    canvas.set_width(width.round() as u32);
+    canvas.set_height(height.round() as u32);
+
+    let context: CanvasRenderingContext2d = canvas
+        .get_context("2d")?
+        .ok_or_else(|| JsValue::from_str("no 2d context"))?
+        .dyn_into()?;
+    // JPEG has no alpha channel, so give transparent images a white backdrop.
+    context.set_fill_style_str("#ffffff");
+    context.fill_rect(0.0, 0.0, width, height);
+    context.draw_image_with_html_image_element_and_dw_and_dh(image, 0.0, 0.0, width, height)?;
+
+    canvas.to_data_url_with_type_and_encoder_options("image/jpeg", &JsValue::from_f64(quality))
+}
+
+/// Copies a GIF without its comment blocks and without application blocks
+/// other than the animation loop settings, which is where editors put XMP.
+/// Returns `None` if the file isn't a well-formed GIF.
+fn strip_gif_metadata(gif: &[u8]) -> Option<Vec<u8>> {
+    if !gif.starts_with(b"GIF87a") && !gif.starts_with(b"GIF89a") {
+        return None;
+    }
+    let flags = *gif.get(10)?;
+    let mut pos = 13 + color_table_len(flags);
+    let mut out = gif.get(..pos)?.to_vec();
+    loop {
+        match *gif.get(pos)? {
+            0x3B => {
+                out.push(0x3B);
+                return Some(out);
+            }
+            0x21 => {
+                let end = skip_sub_blocks(gif, pos + 2)?;
+                let keep = match *gif.get(pos + 1)? {
+                    0xFE => false,
+                    0xFF => matches!(
+                        gif.get(pos + 3..pos + 14),
+                        Some(b"NETSCAPE2.0") | Some(b"ANIMEXTS1.0")
+                    ),
+                    _ => true,
+                };
+                if keep {
+                    out.extend_from_slice(gif.get(pos..end)?);
+                }
+                pos = end;
+            }
+            0x2C => {
+                let flags = *gif.get(pos + 9)?;
+                // Skip the descriptor, local color table and LZW code size.
+                let end = skip_sub_blocks(gif, pos + 11 + color_table_len(flags))?;
+                out.extend_from_slice(gif.get(pos..end)?);
+                pos = end;
+            }
+            _ => return None,
+        }
+    }
+}
+
+fn color_table_len(flags: u8) -> usize {
+    if flags & 0x80 == 0 {
+        0
+    } else {
+        3 << ((flags & 0x07) + 1)
+    }
+}
+
+fn skip_sub_blocks(gif: &[u8], mut pos: usize) -> Option<usize> {
+    loop {
+        let len = *gif.get(pos)? as usize;
+        pos += 1 + len;
+        if len == 0 {
+            return Some(pos);
+        }
+    }
+}
+
+fn base64(bytes: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut out = String::with_capacity(bytes.len().div_ceil(3) * 4);
+    for chunk in bytes.chunks(3) {
+        let n = chunk
+            .iter()
+            .enumerate()
+            .fold(0u32, |n, (i, byte)| n | (*byte as u32) << (16 - 8 * i));
+        for i in 0..4 {
+            if i <= chunk.len() {
+                out.push(ALPHABET[(n >> (18 - 6 * i)) as usize & 63] as char);
+            } else {
+                out.push('=');
+            }
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HEADER: &[u8] = b"GIF89a\x01\x00\x01\x00\x80\x00\x00\x00\x00\x00\xff\xff\xff";
+    const COMMENT: &[u8] = b"\x21\xfe\x03hi!\x00";
+    const XMP: &[u8] = b"\x21\xff\x0bXMP DataXMP\x02<x\x00";
+    const LOOP: &[u8] = b"\x21\xff\x0bNETSCAPE2.0\x03\x01\x00\x00\x00";
+    const FRAME: &[u8] = b"\x21\xf9\x04\x00\x00\x00\x00\x00\x2c\x00\x00\x00\x00\x01\x00\x01\x00\x00\x02\x02\x44\x01\x00";
+
+    fn gif(blocks: &[&[u8]]) -> Vec<u8> {
+        let mut gif = HEADER.to_vec();
+        for block in blocks {
+            gif.extend_from_slice(block);
+        }
+        gif.push(0x3B);
+        gif
+    }
+
+    #[test]
+    fn comments_and_xmp_are_stripped() {
+        let stripped = strip_gif_metadata(&gif(&[COMMENT, XMP, FRAME])).unwrap();
+        assert_eq!(stripped, gif(&[FRAME]));
+    }
+
+    #[test]
+    fn loop_settings_are_kept() {
+        let original = gif(&[LOOP, FRAME, FRAME]);
+        assert_eq!(strip_gif_metadata(&original).unwrap(), original);
+        let with_comment = gif(&[LOOP, COMMENT, FRAME]);
+        assert_eq!(strip_gif_metadata(&with_comment).unwrap(), gif(&[LOOP, FRAME]));
+    }
+
+    #[test]
+    fn truncated_or_foreign_files_are_refused() {
+        let whole = gif(&[COMMENT, FRAME]);
+        for len in [0, 6, 12, HEADER.len(), HEADER.len() + 4, whole.len() - 1] {
+            assert_eq!(strip_gif_metadata(&whole[..len]), None, "{} bytes", len);
+        }
+        assert_eq!(strip_gif_metadata(b"\x89PNG\r\n\x1a\n"), None);
+    }
+
+    #[test]
+    fn base64_pads_partial_groups() {
+        assert_eq!(base64(b""), "");
+        assert_eq!(base64(b"M"), "TQ==");
+        assert_eq!(base64(b"Ma"), "TWE=");
+        assert_eq!(base64(b"Man"), "TWFu");
+        assert_eq!(base64(&[0xFF, 0xFE, 0xFD, 0x00]), "//79AA==");
+    }
+}
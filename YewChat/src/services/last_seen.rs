@@ -0,0 +1,98 @@
+use gloo::storage::{LocalStorage, Storage};
+use serde::{Deserialize, Serialize};
+
+const STORAGE_KEY: &str = "yewchat.last_seen";
+/// People not seen for this long drop out of the roster.
+const RETENTION_MS: f64 = 7.0 * 24.0 * 60.0 * 60.0 * 1000.0;
+const MAX_ENTRIES: usize = 200;
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SeenUser {
+    pub name: String,
+    pub first_seen: f64,
+    pub last_seen: f64,
+}
+
+/// Persisted so people who just left can still be found after a reload.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct LastSeen {
+    users: Vec<SeenUser>,
+}
+
+impl LastSeen {
+    pub fn load() -> Self {
+        let users: Vec<SeenUser> = LocalStorage::get(STORAGE_KEY).unwrap_or_default();
+        let mut last_seen = Self { users };
+        last_seen.prune(js_sys::Date::now());
+        last_seen
+    }
+
+    pub fn save(&self) {
+        if let Err(e) = LocalStorage::set(STORAGE_KEY, &self.users) {
+            log::error!("failed to save last-seen times: {:?}", e);
+        }
+    }
+
+    pub fn touch(&mut self, name: &str, now: f64) {
+        match self.users.iter_mut().find(|u| u.name == name) {
+            Some(user) => user.last_seen = user.last_seen.max(now),
+            None => self.users.push(SeenUser {
+                name: name.to_string(),
+                first_seen: now,
+                last_seen: now,
+            }),
+        }
+        self.prune(now);
+    }
+
+    pub fn get(&self, name: &str) -> Option<&SeenUser> {
+        self.users.iter().find(|u| u.name == name)
+    }
+
+    pub fn rename(&mut self, from: &str, to: &str) {
+        let previous = match self.users.iter().position(|u| u.name == from) {
+            Some(index) => self.users.remove(index),
+            None => return,
+        };
+        match self.users.iter_mut().find(|u| u.name == to) {
+            Some(user) => {
+                user.first_seen = user.first_seen.min(previous.first_seen);
+                user.last_seen = user.last_seen.max(previous.last_seen);
+            }
+            None => self.users.push(SeenUser {
+                name: to.to_string(),
+                ..previous
+            }),
+        }
+    }
+
+    pub fn offline<'a>(&'a self, online: &[&str]) -> Vec<&'a SeenUser> {
+        let mut offline: Vec<&SeenUser> = self
+            .users
+            .iter()
+            .filter(|u| !online.contains(&u.name.as_str()))
+            .collect();
+        offline.sort_by(|a, b| b.last_seen.total_cmp(&a.last_seen));
+        offline
+    }
+
+    fn prune(&mut self, now: f64) {
+        self.users.retain(|u| now - u.last_seen < RETENTION_MS);
+        if self.users.len() > MAX_ENTRIES {
+            self.users.sort_by(|a, b| b.last_seen.total_cmp(&a.last_seen));
+            self.users.truncate(MAX_ENTRIES);
+        }
+    }
+}
+
+pub fn ago(then: f64, now: f64) -> String {
+    let minutes = ((now - then).max(0.0) / 60_000.0) as u64;
+    let (count, unit) = match minutes {
+        0 => return "just now".to_string(),
+        1..=59 => (minutes, "minute"),
+        60..=1439 => (minutes / 60, "hour"),
+        _ => (minutes / 1440, "day"),
+    };
+    format!("{} {}{} ago", count, unit, if count == 1 { "" } else { "s" })
+}
@@ -0,0 +1,48 @@
+/// Kind of embeddable media detected in a chat message.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MediaKind {
+    Image,
+    Video,
+}
+
+/// A piece of media shared in the conversation, in timeline order.
+#[derive(Clone, Debug, PartialEq)]
+pub struct MediaItem {
+    pub kind: MediaKind,
+    pub url: String,
+    pub from: String,
+    /// Id of the timeline message that shared it.
+    pub message_id: u64,
+}
+
+const IMAGE_EXTENSIONS: [&str; 6] = [".gif", ".png", ".jpg", ".jpeg", ".webp", ".svg"];
+const VIDEO_EXTENSIONS: [&str; 3] = [".mp4", ".webm", ".ogv"];
+
+/// Detects whether a message body is a link to an image or video.
+pub fn media_kind(message: &str) -> Option<MediaKind> {
+    let message = message.trim();
+    if message.contains(char::is_whitespace) {
+        return None;
+    }
+
+    if message.starts_with("data:image/") {
+        return Some(MediaKind::Image);
+    }
+    if message.starts_with("data:video/") {
+        return Some(MediaKind::Video);
+    }
+
+    // Ignore query strings and fragments so `cat.png?size=large` still counts.
+    let path = message
+        .split(['?', '#'])
+        .next()
+        .unwrap_or_default()
+        .to_lowercase();
+    if IMAGE_EXTENSIONS.iter().any(|ext| path.ends_with(ext)) {
+        Some(MediaKind::Image)
+    } else if VIDEO_EXTENSIONS.iter().any(|ext| path.ends_with(ext)) {
+        Some(MediaKind::Video)
+    } else {
+        None
+    }
+}
@@ -1,2 +1,25 @@
+pub mod auth;
+pub mod avatar;
+pub mod commands;
+pub mod custom_emoji;
+pub mod dnd;
+pub mod emoji;
 pub mod websocket;
-pub mod event_bus;
\ No newline at end of file
+pub mod event_bus;
+pub mod file_transfer;
+pub mod gestures;
+pub mod history;
+pub mod image;
+pub mod last_seen;
+pub mod media;
+pub mod notifications;
+pub mod object_url;
+pub mod presence;
+pub mod roster;
+pub mod session;
+pub mod settings;
+pub mod sounds;
+pub mod store;
+pub mod unread;
+pub mod username;
+pub mod viewport;
@@ -0,0 +1,216 @@
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+use wasm_bindgen::prelude::*;
+use wasm_bindgen::JsCast;
+use wasm_bindgen_futures::JsFuture;
+use web_sys::{Notification, NotificationOptions, NotificationPermission};
+use yew::Callback;
+
+use crate::services::media::{media_kind, MediaKind};
+
+/// Longest message preview shown in a notification.
+const PREVIEW_CHARS: usize = 120;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum NotifyMode {
+    All,
+    /// Mentions, direct messages and keywords.
+    #[default]
+    Mentions,
+    None,
+}
+
+impl NotifyMode {
+    pub const ALL: [NotifyMode; 3] = [NotifyMode::All, NotifyMode::Mentions, NotifyMode::None];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            NotifyMode::All => "All messages",
+            NotifyMode::Mentions => "Mentions only",
+            NotifyMode::None => "Nothing",
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct NotificationSettings {
+    /// Mode for the main room and any room without its own.
+    pub default_mode: NotifyMode,
+    pub rooms: HashMap<String, NotifyMode>,
+    /// Words that notify like a mention, matched case-insensitively.
+    pub keywords: Vec<String>,
+    /// Set once the user has said "not now" to turning notifications on.
+    pub prompt_dismissed: bool,
+}
+
+impl NotificationSettings {
+    pub fn mode_for(&self, room: Option<&str>) -> NotifyMode {
+        room.and_then(|room| self.rooms.get(room))
+            .copied()
+            .unwrap_or(self.default_mode)
+    }
+
+    pub fn set_mode(&mut self, room: Option<&str>, mode: NotifyMode) {
+        match room {
+            Some(room) => {
+                self.rooms.insert(room.to_string(), mode);
+            }
+            None => self.default_mode = mode,
+        }
+    }
+}
+
+/// Why a message deserves a notification.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Trigger {
+    Direct,
+    Mention,
+    Keyword(String),
+    /// The room notifies about every message.
+    Message,
+}
+
+/// Decides whether a message from `from` should notify `me`. Our own
+/// messages never do.
+pub fn trigger(
+    settings: &NotificationSettings,
+    room: Option<&str>,
+    me: &str,
+    from: &str,
+    to: Option<&str>,
+    text: &str,
+) -> Option<Trigger> {
+    if from == me {
+        return None;
+    }
+    let mode = settings.mode_for(room);
+    if mode == NotifyMode::None {
+        return None;
+    }
+    if to == Some(me) {
+        return Some(Trigger::Direct);
+    }
+    if mentions(text, me) {
+        return Some(Trigger::Mention);
+    }
+    if let Some(keyword) = settings
+        .keywords
+        .iter()
+        .find(|keyword| contains_word(text, keyword))
+    {
+        return Some(Trigger::Keyword(keyword.clone()));
+    }
+    (mode == NotifyMode::All).then_some(Trigger::Message)
+}
+
+pub fn mentions(text: &str, name: &str) -> bool {
+    !name.is_empty() && contains_word(text, &format!("@{}", name))
+}
+
+/// Case-insensitive search for `word` not surrounded by other word characters.
+fn contains_word(text: &str, word: &str) -> bool {
+    let word = word.trim().to_lowercase();
+    if word.is_empty() {
+        return false;
+    }
+    let text = text.to_lowercase();
+    let is_word_char = |c: char| c.is_alphanumeric() || c == '_';
+    text.match_indices(&word).any(|(start, _)| {
+        let before = text[..start].chars().next_back();
+        let after = text[start + word.len()..].chars().next();
+        !before.is_some_and(is_word_char) && !after.is_some_and(is_word_char)
+    })
+}
+
+/// Short text describing a message for a notification body.
+pub fn preview(text: &str) -> String {
+    match media_kind(text) {
+        Some(MediaKind::Image) => return "Sent an image".to_string(),
+        Some(MediaKind::Video) => return "Sent a video".to_string(),
+        None => {}
+    }
+    let text = text.trim();
+    if text.chars().count() > PREVIEW_CHARS {
+        let cut: String = text.chars().take(PREVIEW_CHARS).collect();
+        format!("{}…", cut.trim_end())
+    } else {
+        text.to_string()
+    }
+}
+
+pub fn supported() -> bool {
+    web_sys::window()
+        .map(|window| js_sys::Reflect::has(&window, &JsValue::from_str("Notification")).unwrap_or(false))
+        .unwrap_or(false)
+}
+
+pub fn permission() -> NotificationPermission {
+    if supported() {
+        Notification::permission()
+    } else {
+        NotificationPermission::Denied
+    }
+}
+
+/// Asks the browser for permission. Must be called from a user gesture.
+pub async fn request_permission() -> NotificationPermission {
+    if !supported() {
+        return NotificationPermission::Denied;
+    }
+    match Notification::request_permission() {
+        Ok(promise) => {
+            if let Err(e) = JsFuture::from(promise).await {
+                log::error!("notification permission request failed: {:?}", e);
+            }
+        }
+        Err(e) => log::error!("could not request notification permission: {:?}", e),
+    }
+    permission()
+}
+
+/// A notification on screen. Dropping it closes it.
+pub struct DesktopNotification {
+    notification: Notification,
+    _onclick: Closure<dyn FnMut()>,
+}
+
+impl DesktopNotification {
+    /// Shows a notification if permission was granted. Clicking it focuses
+    /// the tab and then calls `onclick`.
+    pub fn show(title: &str, body: &str, icon: &str, onclick: Callback<()>) -> Option<Self> {
+        if permission() != NotificationPermission::Granted {
+            return None;
+        }
+        let options = NotificationOptions::new();
+        options.set_body(body);
+        options.set_icon(icon);
+        let notification = match Notification::new_with_options(title, &options) {
+            Ok(notification) => notification,
+            Err(e) => {
+                log::error!("failed to show notification: {:?}", e);
+                return None;
+            }
+        };
+        let onclick = Closure::wrap(Box::new(move || {
+            if let Some(window) = web_sys::window() {
+                let _ = window.focus();
+            }
+            onclick.emit(());
+        }) as Box<dyn FnMut()>);
+        notification.set_onclick(Some(onclick.as_ref().unchecked_ref()));
+        Some(Self {
+            notification,
+            _onclick: onclick,
+        })
+    }
+}
+
+impl Drop for DesktopNotification {
+    fn drop(&mut self) {
+        self.notification.set_onclick(None);
+        self.notification.close();
+    }
+}
@@ -0,0 +1,30 @@
+use std::ops::Deref;
+
+use gloo::file::Blob;
+
+/// A `blob:` URL for in-memory data, revoked when dropped so the browser
+/// can free the data. The locked gloo-file predates its own `ObjectUrl`.
+pub struct ObjectUrl(String);
+
+impl<B: Into<Blob>> From<B> for ObjectUrl {
+    fn from(blob: B) -> Self {
+        let blob: web_sys::Blob = blob.into().into();
+        let url = web_sys::Url::create_object_url_with_blob(&blob)
+            .expect("creating an object URL for a blob never fails");
+        Self(url)
+    }
+}
+
+impl Deref for ObjectUrl {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl Drop for ObjectUrl {
+    fn drop(&mut self) {
+        let _ = web_sys::Url::revoke_object_url(&self.0);
+    }
+}
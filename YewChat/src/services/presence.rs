@@ -0,0 +1,152 @@
+use std::cell::Cell;
+use std::rc::Rc;
+
+use gloo::events::EventListener;
+use gloo::timers::callback::Interval;
+use serde::{Deserialize, Serialize};
+use yew::Callback;
+
+pub const MAX_STATUS_TEXT: usize = 100;
+const IDLE_CHECK_MS: u32 = 15_000;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Status {
+    #[default]
+    Online,
+    Away,
+    Busy,
+    /// Connected, but hidden from everyone else's user list.
+    Invisible,
+}
+
+impl Status {
+    pub const ALL: [Status; 4] = [Status::Online, Status::Away, Status::Busy, Status::Invisible];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            Status::Online => "Online",
+            Status::Away => "Away",
+            Status::Busy => "Do not disturb",
+            Status::Invisible => "Invisible",
+        }
+    }
+
+    pub fn color(&self) -> &'static str {
+        match self {
+            Status::Online => "bg-green-500",
+            Status::Away => "bg-amber-400",
+            Status::Busy => "bg-red-500",
+            Status::Invisible => "bg-gray-400",
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Presence {
+    pub status: Status,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub text: Option<String>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(from = "UserEntry")]
+pub struct OnlineUser {
+    pub name: String,
+    #[serde(flatten)]
+    pub presence: Presence,
+    #[serde(default)]
+    pub account: bool,
+}
+
+/// Servers from before presence send bare nicknames.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum UserEntry {
+    Name(String),
+    Full {
+        name: String,
+        #[serde(default)]
+        account: bool,
+        #[serde(flatten)]
+        presence: Presence,
+    },
+}
+
+impl From<UserEntry> for OnlineUser {
+    fn from(entry: UserEntry) -> Self {
+        match entry {
+            UserEntry::Name(name) => OnlineUser {
+                name,
+                presence: Presence::default(),
+                account: false,
+            },
+            UserEntry::Full {
+                name,
+                account,
+                presence,
+            } => OnlineUser {
+                name,
+                presence,
+                account,
+            },
+        }
+    }
+}
+
+/// Reports idle after `minutes` without input, or as soon as the tab is hidden.
+pub struct IdleWatcher {
+    _listeners: Vec<EventListener>,
+    _interval: Interval,
+}
+
+impl IdleWatcher {
+    pub fn new(minutes: u32, onchange: Callback<bool>) -> Self {
+        let document = web_sys::window()
+            .and_then(|w| w.document())
+            .expect("document to be available");
+        let last_input = Rc::new(Cell::new(js_sys::Date::now()));
+        let idle = Rc::new(Cell::new(false));
+
+        let report = move |now_idle: bool| {
+            if idle.replace(now_idle) != now_idle {
+                onchange.emit(now_idle);
+            }
+        };
+
+        let mut listeners = vec![];
+        for event in ["keydown", "pointerdown", "pointermove", "wheel"] {
+            let last_input = last_input.clone();
+            let report = report.clone();
+            listeners.push(EventListener::new(&document, event, move |_| {
+                last_input.set(js_sys::Date::now());
+                report(false);
+            }));
+        }
+        {
+            let last_input = last_input.clone();
+            let report = report.clone();
+            let target = document.clone();
+            listeners.push(EventListener::new(&target, "visibilitychange", move |_| {
+                if document.hidden() {
+                    report(true);
+                } else {
+                    last_input.set(js_sys::Date::now());
+                    report(false);
+                }
+            }));
+        }
+
+        let limit_ms = minutes as f64 * 60_000.0;
+        let interval = Interval::new(IDLE_CHECK_MS, move || {
+            if js_sys::Date::now() - last_input.get() >= limit_ms {
+                report(true);
+            }
+        });
+
+        Self {
+            _listeners: listeners,
+            _interval: interval,
+        }
+    }
+}
@@ -0,0 +1,94 @@
+use std::collections::{HashMap, HashSet};
+
+/// How long a departure is held back in case the user comes straight back.
+pub const FLAP_WINDOW_MS: f64 = 10_000.0;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum RosterEvent {
+    Joined(String),
+    Left(String),
+    /// Left and came back within [`FLAP_WINDOW_MS`].
+    Reconnected(String),
+}
+
+impl RosterEvent {
+    pub fn describe(&self) -> String {
+        match self {
+            RosterEvent::Joined(name) => format!("{} joined", name),
+            RosterEvent::Left(name) => format!("{} left", name),
+            RosterEvent::Reconnected(name) => format!("{} reconnected", name),
+        }
+    }
+}
+
+/// Departures wait out [`FLAP_WINDOW_MS`] before being reported, so a flaky
+/// connection shows up as one "reconnected" line instead of a left/joined pair.
+#[derive(Default)]
+pub struct RosterTracker {
+    previous: Option<HashSet<String>>,
+    departed: HashMap<String, f64>,
+}
+
+impl RosterTracker {
+    /// The first snapshot after [`reset`](Self::reset) only sets the baseline.
+    pub fn update(&mut self, current: &[String], now: f64) -> Vec<RosterEvent> {
+        let current: HashSet<String> = current.iter().cloned().collect();
+        let previous = match self.previous.replace(current.clone()) {
+            Some(previous) => previous,
+            None => {
+                self.departed.retain(|name, _| !current.contains(name));
+                return vec![];
+            }
+        };
+
+        let mut events = vec![];
+        for name in previous.difference(&current) {
+            self.departed.insert(name.clone(), now);
+        }
+        let mut joined: Vec<&String> = current.difference(&previous).collect();
+        joined.sort();
+        for name in joined {
+            if self.departed.remove(name).is_some() {
+                events.push(RosterEvent::Reconnected(name.clone()));
+            } else {
+                events.push(RosterEvent::Joined(name.clone()));
+            }
+        }
+        events
+    }
+
+    pub fn flush(&mut self, now: f64) -> Vec<RosterEvent> {
+        let mut gone: Vec<String> = self
+            .departed
+            .iter()
+            .filter(|(_, left_at)| now - **left_at >= FLAP_WINDOW_MS)
+            .map(|(name, _)| name.clone())
+            .collect();
+        gone.sort();
+        for name in &gone {
+            self.departed.remove(name);
+        }
+        gone.into_iter().map(RosterEvent::Left).collect()
+    }
+
+    pub fn next_flush_in(&self, now: f64) -> Option<u32> {
+        self.departed
+            .values()
+            .map(|left_at| (left_at + FLAP_WINDOW_MS - now).max(0.0))
+            .reduce(f64::min)
+            .map(|ms| ms.ceil() as u32)
+    }
+
+    pub fn rename(&mut self, from: &str, to: &str) {
+        if let Some(previous) = &mut self.previous {
+            if previous.remove(from) {
+                previous.insert(to.to_string());
+            }
+        }
+    }
+
+    /// After our own connection drops, so everyone doesn't appear to rejoin.
+    pub fn reset(&mut self) {
+        self.previous = None;
+    }
+}
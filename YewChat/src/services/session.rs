@@ -0,0 +1,112 @@
+use gloo::storage::{LocalStorage, SessionStorage, Storage};
+use serde::{Deserialize, Serialize};
+
+use crate::services::auth::AuthToken;
+use crate::services::username::Registration;
+
+const REDIRECT_KEY: &str = "yewchat.redirect";
+const REJECTION_KEY: &str = "yewchat.rejection";
+const IDENTITY_KEY: &str = "yewchat.identity";
+const AUTH_KEY: &str = "yewchat.auth";
+
+/// Where a visitor was headed before being sent to the login page.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct RedirectTarget {
+    pub path: String,
+    pub query: Vec<(String, String)>,
+}
+
+/// Identity kept across reloads when "remember me" was ticked at login.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RememberedUser {
+    pub username: String,
+    /// Lets the server hand our nickname back even if a stale connection
+    /// still holds it.
+    #[serde(default)]
+    pub resume_token: Option<String>,
+    /// Where the user was last, reopened on the next visit.
+    #[serde(default)]
+    pub last_location: Option<RedirectTarget>,
+    /// Kept so a signed-in user stays signed in across browser restarts.
+    #[serde(default)]
+    pub auth: Option<AuthToken>,
+}
+
+/// Remembers the deep link so it can be restored after login. Kept in
+/// session storage so it survives a reload of the login page.
+pub fn save_redirect(target: &RedirectTarget) {
+    if let Err(e) = SessionStorage::set(REDIRECT_KEY, target) {
+        log::error!("failed to save redirect target: {:?}", e);
+    }
+}
+
+pub fn take_redirect() -> Option<RedirectTarget> {
+    let target = SessionStorage::get(REDIRECT_KEY).ok();
+    SessionStorage::delete(REDIRECT_KEY);
+    target
+}
+
+/// Hands a refused registration over to the login page.
+pub fn save_rejection(registration: &Registration) {
+    if let Err(e) = SessionStorage::set(REJECTION_KEY, registration) {
+        log::error!("failed to save registration error: {:?}", e);
+    }
+}
+
+pub fn take_rejection() -> Option<Registration> {
+    let registration = SessionStorage::get(REJECTION_KEY).ok();
+    SessionStorage::delete(REJECTION_KEY);
+    registration
+}
+
+pub fn auth_token() -> Option<AuthToken> {
+    SessionStorage::get(AUTH_KEY)
+        .ok()
+        .or_else(|| remembered().and_then(|identity| identity.auth))
+        .filter(|token: &AuthToken| !token.is_expired())
+}
+
+/// Stores a new sign-in token, replacing the old one everywhere.
+pub fn save_auth(token: &AuthToken) {
+    if let Err(e) = SessionStorage::set(AUTH_KEY, token) {
+        log::error!("failed to save sign-in token: {:?}", e);
+    }
+    update_remembered(|identity| identity.auth = Some(token.clone()));
+}
+
+/// Signs out of the account while keeping the rest of the session, e.g.
+/// when continuing as a guest.
+pub fn clear_auth() {
+    SessionStorage::delete(AUTH_KEY);
+    update_remembered(|identity| identity.auth = None);
+}
+
+pub fn clear() {
+    SessionStorage::delete(REDIRECT_KEY);
+    SessionStorage::delete(REJECTION_KEY);
+    SessionStorage::delete(AUTH_KEY);
+}
+
+pub fn remembered() -> Option<RememberedUser> {
+    LocalStorage::get(IDENTITY_KEY).ok()
+}
+
+/// Keeps `identity` in local storage so the next visit skips the login page.
+pub fn remember(identity: &RememberedUser) {
+    if let Err(e) = LocalStorage::set(IDENTITY_KEY, identity) {
+        log::error!("failed to remember identity: {:?}", e);
+    }
+}
+
+/// Changes the remembered identity, if the user asked to be remembered.
+pub fn update_remembered(f: impl FnOnce(&mut RememberedUser)) {
+    if let Some(mut identity) = remembered() {
+        f(&mut identity);
+        remember(&identity);
+    }
+}
+
+pub fn forget() {
+    LocalStorage::delete(IDENTITY_KEY);
+}
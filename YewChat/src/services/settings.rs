@@ -0,0 +1,70 @@
+use gloo::storage::{LocalStorage, Storage};
+use serde::{Deserialize, Serialize};
+
+use crate::services::avatar::AvatarStyle;
+use crate::services::dnd::DndSettings;
+use crate::services::emoji::SkinTone;
+use crate::services::notifications::NotificationSettings;
+use crate::services::sounds::SoundSettings;
+
+const STORAGE_KEY: &str = "yewchat.settings";
+
+/// User preferences, persisted in local storage between sessions.
+///
+/// Missing fields fall back to their defaults so older saved settings keep
+/// loading after new options are added.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    pub avatar_style: AvatarStyle,
+    pub skin_tone: SkinTone,
+    /// Minutes without input before showing as away; 0 turns it off.
+    pub auto_away_minutes: u32,
+    /// Show "bob joined" and "carol left" lines in the timeline.
+    pub show_join_leave: bool,
+    /// Nicknames whose messages are hidden.
+    pub ignored: Vec<String>,
+    pub notifications: NotificationSettings,
+    pub sounds: SoundSettings,
+    pub dnd: DndSettings,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            avatar_style: AvatarStyle::default(),
+            skin_tone: SkinTone::default(),
+            auto_away_minutes: 5,
+            show_join_leave: true,
+            ignored: vec![],
+            notifications: NotificationSettings::default(),
+            sounds: SoundSettings::default(),
+            dnd: DndSettings::default(),
+        }
+    }
+}
+
+impl Settings {
+    pub fn load() -> Self {
+        LocalStorage::get(STORAGE_KEY).unwrap_or_default()
+    }
+
+    pub fn is_ignored(&self, name: &str) -> bool {
+        self.ignored.iter().any(|n| n == name)
+    }
+
+    /// Starts or stops ignoring `name`.
+    pub fn toggle_ignored(&mut self, name: &str) {
+        if self.is_ignored(name) {
+            self.ignored.retain(|n| n != name);
+        } else {
+            self.ignored.push(name.to_string());
+        }
+    }
+
+    pub fn save(&self) {
+        if let Err(e) = LocalStorage::set(STORAGE_KEY, self) {
+            log::error!("failed to save settings: {:?}", e);
+        }
+    }
+}
@@ -0,0 +1,179 @@
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+use web_sys::{AudioContext, OscillatorType};
+
+/// Shortest gap between two plays of the same cue.
+const SAME_CUE_GAP_MS: f64 = 2_000.0;
+/// Shortest gap between any two cues, so they never pile up.
+const ANY_CUE_GAP_MS: f64 = 300.0;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum SoundEvent {
+    Message,
+    Mention,
+    Direct,
+    Connect,
+    Disconnect,
+}
+
+impl SoundEvent {
+    pub const ALL: [SoundEvent; 5] = [
+        SoundEvent::Message,
+        SoundEvent::Mention,
+        SoundEvent::Direct,
+        SoundEvent::Connect,
+        SoundEvent::Disconnect,
+    ];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            SoundEvent::Message => "New message",
+            SoundEvent::Mention => "Mention",
+            SoundEvent::Direct => "Direct message",
+            SoundEvent::Connect => "Reconnected",
+            SoundEvent::Disconnect => "Connection lost",
+        }
+    }
+
+    /// The notes of the cue as (frequency in Hz, start and length in seconds).
+    fn notes(&self) -> &'static [(f32, f64, f64)] {
+        match self {
+            SoundEvent::Message => &[(660.0, 0.0, 0.12)],
+            SoundEvent::Mention => &[(880.0, 0.0, 0.1), (1175.0, 0.12, 0.14)],
+            SoundEvent::Direct => &[(784.0, 0.0, 0.1), (988.0, 0.1, 0.1), (1319.0, 0.2, 0.16)],
+            SoundEvent::Connect => &[(523.0, 0.0, 0.08), (784.0, 0.08, 0.14)],
+            SoundEvent::Disconnect => &[(784.0, 0.0, 0.08), (523.0, 0.08, 0.18)],
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Cue {
+    pub enabled: bool,
+    pub volume: f32,
+}
+
+impl Default for Cue {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            volume: 0.5,
+        }
+    }
+}
+
+/// Sound preferences, stored with the other settings. Off until turned on.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SoundSettings {
+    pub enabled: bool,
+    pub message: Cue,
+    pub mention: Cue,
+    pub direct: Cue,
+    pub connect: Cue,
+    pub disconnect: Cue,
+}
+
+impl SoundSettings {
+    pub fn cue(&self, event: SoundEvent) -> &Cue {
+        match event {
+            SoundEvent::Message => &self.message,
+            SoundEvent::Mention => &self.mention,
+            SoundEvent::Direct => &self.direct,
+            SoundEvent::Connect => &self.connect,
+            SoundEvent::Disconnect => &self.disconnect,
+        }
+    }
+
+    pub fn cue_mut(&mut self, event: SoundEvent) -> &mut Cue {
+        match event {
+            SoundEvent::Message => &mut self.message,
+            SoundEvent::Mention => &mut self.mention,
+            SoundEvent::Direct => &mut self.direct,
+            SoundEvent::Connect => &mut self.connect,
+            SoundEvent::Disconnect => &mut self.disconnect,
+        }
+    }
+}
+
+/// Plays short synthesized cues, so no audio files have to be shipped.
+#[derive(Default)]
+pub struct SoundPlayer {
+    /// Created on first use; browsers only let it start after user input.
+    context: Option<AudioContext>,
+    last_played: HashMap<SoundEvent, f64>,
+    last_any: f64,
+}
+
+impl SoundPlayer {
+    /// Plays the cue for `event` if the settings allow it, the window isn't
+    /// focused, and it hasn't played too recently.
+    pub fn play(&mut self, event: SoundEvent, settings: &SoundSettings) {
+        let cue = settings.cue(event);
+        if !settings.enabled || !cue.enabled || window_focused() {
+            return;
+        }
+        let now = js_sys::Date::now();
+        let recent = self
+            .last_played
+            .get(&event)
+            .is_some_and(|last| now - last < SAME_CUE_GAP_MS);
+        if recent || now - self.last_any < ANY_CUE_GAP_MS {
+            return;
+        }
+        self.last_played.insert(event, now);
+        self.last_any = now;
+        self.sound(event, cue.volume);
+    }
+
+    /// Plays a cue regardless of focus and rate limits, for trying it out.
+    pub fn preview(&mut self, event: SoundEvent, volume: f32) {
+        self.sound(event, volume);
+    }
+
+    fn sound(&mut self, event: SoundEvent, volume: f32) {
+        if volume <= 0.0 {
+            return;
+        }
+        if let Err(e) = self.try_sound(event, volume) {
+            log::error!("failed to play sound: {:?}", e);
+        }
+    }
+
+    fn try_sound(&mut self, event: SoundEvent, volume: f32) -> Result<(), wasm_bindgen::JsValue> {
+        if self.context.is_none() {
+            self.context = Some(AudioContext::new()?);
+        }
+        let context = self.context.as_ref().unwrap();
+        // Left suspended if it was created before any user input.
+        let _ = context.resume();
+
+        let start = context.current_time();
+        for (frequency, offset, length) in event.notes() {
+            let at = start + offset;
+            let oscillator = context.create_oscillator()?;
+            oscillator.set_type(OscillatorType::Sine);
+            oscillator.frequency().set_value(*frequency);
+
+            // A quick fade in and out avoids clicks.
+            let gain = context.create_gain()?;
+            gain.gain().set_value_at_time(0.0, at)?;
+            gain.gain().linear_ramp_to_value_at_time(volume, at + 0.01)?;
+            gain.gain().exponential_ramp_to_value_at_time(0.0001, at + length)?;
+
+            oscillator.connect_with_audio_node(&gain)?;
+            gain.connect_with_audio_node(&context.destination())?;
+            oscillator.start_with_when(at)?;
+            oscillator.stop_with_when(at + length)?;
+        }
+        Ok(())
+    }
+}
+
+fn window_focused() -> bool {
+    web_sys::window()
+        .and_then(|w| w.document())
+        .is_some_and(|d| !d.hidden() && d.has_focus().unwrap_or(false))
+}
@@ -0,0 +1,239 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use yew::prelude::*;
+
+use crate::services::session;
+use crate::services::settings::Settings;
+
+/// Everything shared between pages. Only changed through [`dispatch`].
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct AppState {
+    pub user: UserState,
+    pub settings: Settings,
+    pub connection: Connection,
+    pub rooms: Rooms,
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct UserState {
+    pub username: String,
+}
+
+impl UserState {
+    pub fn is_signed_in(&self) -> bool {
+        !self.username.is_empty()
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum Connection {
+    #[default]
+    Disconnected,
+    Connecting,
+    Connected,
+    /// The socket dropped and will be reopened shortly.
+    Reconnecting,
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Rooms {
+    /// Room named by the `room` query parameter of the chat page, if any.
+    pub active: Option<String>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Action {
+    SetUsername(String),
+    /// Forgets the user; the settings are kept for whoever signs in next.
+    SignOut,
+    UpdateSettings(Settings),
+    SetConnection(Connection),
+    OpenRoom(Option<String>),
+}
+
+impl AppState {
+    /// State on startup, picking up the remembered user and saved settings.
+    fn initial() -> Self {
+        Self {
+            user: UserState {
+                username: session::remembered()
+                    .map(|identity| identity.username)
+                    .unwrap_or_default(),
+            },
+            settings: Settings::load(),
+            ..Self::default()
+        }
+    }
+
+    /// Applies `action`. Pure, so persisting the result is up to [`dispatch`].
+    pub fn reduce(&self, action: Action) -> Self {
+        let mut next = self.clone();
+        match action {
+            Action::SetUsername(username) => next.user.username = username,
+            Action::SignOut => {
+                next.user = UserState::default();
+                next.connection = Connection::Disconnected;
+                next.rooms = Rooms::default();
+            }
+            Action::UpdateSettings(settings) => next.settings = settings,
+            Action::SetConnection(connection) => next.connection = connection,
+            Action::OpenRoom(room) => next.rooms.active = room,
+        }
+        next
+    }
+}
+
+type Listener = Rc<dyn Fn(&AppState, &AppState)>;
+
+struct Store {
+    state: Rc<AppState>,
+    listeners: Vec<(usize, Listener)>,
+    next_id: usize,
+}
+
+thread_local! {
+    static STORE: RefCell<Store> = RefCell::new(Store {
+        state: Rc::new(AppState::initial()),
+        listeners: vec![],
+        next_id: 0,
+    });
+}
+
+pub fn state() -> Rc<AppState> {
+    STORE.with(|store| store.borrow().state.clone())
+}
+
+/// Runs `action` through the reducer and notifies the subscribers whose
+/// slice changed.
+pub fn dispatch(action: Action) {
+    let (previous, next, listeners) = STORE.with(|store| {
+        let mut store = store.borrow_mut();
+        let previous = store.state.clone();
+        store.state = Rc::new(previous.reduce(action));
+        let listeners: Vec<Listener> = store.listeners.iter().map(|(_, l)| l.clone()).collect();
+        (previous, store.state.clone(), listeners)
+    });
+    if previous.settings != next.settings {
+        next.settings.save();
+    }
+    // Outside the borrow, so listeners may dispatch in turn.
+    for listener in listeners {
+        listener(&previous, &next);
+    }
+}
+
+/// Keeps a subscription alive; dropping it unsubscribes.
+pub struct Subscription {
+    id: usize,
+}
+
+impl Drop for Subscription {
+    fn drop(&mut self) {
+        let id = self.id;
+        STORE.with(|store| store.borrow_mut().listeners.retain(|(i, _)| *i != id));
+    }
+}
+
+/// Calls `callback` with the new value of `select` whenever it changes.
+pub fn subscribe<T: PartialEq + 'static>(
+    select: fn(&AppState) -> T,
+    callback: Callback<T>,
+) -> Subscription {
+    let listener: Listener = Rc::new(move |previous, next| {
+        let value = select(next);
+        if select(previous) != value {
+            callback.emit(value);
+        }
+    });
+    STORE.with(|store| {
+        let mut store = store.borrow_mut();
+        let id = store.next_id;
+        store.next_id += 1;
+        store.listeners.push((id, listener));
+        Subscription { id }
+    })
+}
+
+/// Hook returning one slice of the state, re-rendering only when it changes.
+pub fn use_slice<T: Clone + PartialEq + 'static>(select: fn(&AppState) -> T) -> T {
+    let value = use_state(|| select(&state()));
+    {
+        let value = value.clone();
+        use_effect_with_deps(
+            move |_| {
+                // Catch anything dispatched between the first render and now.
+                let current = select(&state());
+                if *value != current {
+                    value.set(current);
+                }
+                let subscription = subscribe(select, Callback::from(move |v| value.set(v)));
+                move || drop(subscription)
+            },
+            (),
+        );
+    }
+    (*value).clone()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn signed_in() -> AppState {
+        AppState {
+            user: UserState {
+                username: "alice".into(),
+            },
+            connection: Connection::Connected,
+            rooms: Rooms {
+                active: Some("general".into()),
+            },
+            ..AppState::default()
+        }
+    }
+
+    #[test]
+    fn set_username_replaces_the_user() {
+        let next = AppState::default().reduce(Action::SetUsername("bob".into()));
+        assert_eq!(next.user.username, "bob");
+        assert!(next.user.is_signed_in());
+    }
+
+    #[test]
+    fn sign_out_keeps_settings() {
+        let mut state = signed_in();
+        state.settings.show_join_leave = false;
+        let next = state.reduce(Action::SignOut);
+        assert!(!next.user.is_signed_in());
+        assert_eq!(next.connection, Connection::Disconnected);
+        assert_eq!(next.rooms, Rooms::default());
+        assert_eq!(next.settings, state.settings);
+    }
+
+    #[test]
+    fn update_settings_only_touches_settings() {
+        let state = signed_in();
+        let settings = Settings {
+            auto_away_minutes: 0,
+            ..Settings::default()
+        };
+        let next = state.reduce(Action::UpdateSettings(settings.clone()));
+        assert_eq!(next.settings, settings);
+        assert_eq!(next.user, state.user);
+    }
+
+    #[test]
+    fn set_connection_tracks_the_socket() {
+        let next = signed_in().reduce(Action::SetConnection(Connection::Reconnecting));
+        assert_eq!(next.connection, Connection::Reconnecting);
+        assert_eq!(next.user.username, "alice");
+    }
+
+    #[test]
+    fn open_room_sets_and_clears_the_active_room() {
+        let next = AppState::default().reduce(Action::OpenRoom(Some("random".into())));
+        assert_eq!(next.rooms.active.as_deref(), Some("random"));
+        assert_eq!(next.reduce(Action::OpenRoom(None)).rooms.active, None);
+    }
+}
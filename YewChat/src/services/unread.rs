@@ -0,0 +1,165 @@
+use std::cell::Cell;
+use std::rc::Rc;
+
+use gloo::events::EventListener;
+use wasm_bindgen::{JsCast, JsValue};
+use web_sys::{CanvasRenderingContext2d, Document, HtmlCanvasElement, HtmlImageElement, HtmlLinkElement};
+
+const TITLE: &str = "Yewchat!";
+const FAVICON: &str = "favicon.svg";
+const ICON_SIZE: f64 = 32.0;
+/// Badge colors: purple for plain messages, red once someone mentions us.
+const BADGE_COLOR: &str = "#7c3aed";
+const MENTION_COLOR: &str = "#dc2626";
+
+#[derive(Clone, Copy, Default)]
+struct Counts {
+    unread: u32,
+    mentions: u32,
+}
+
+/// Counts messages that arrive while the tab is hidden, showing them in the
+/// tab title and as a badge on the favicon until the tab is looked at again.
+/// Dropping it puts the title and favicon back.
+pub struct UnreadTracker {
+    counts: Rc<Cell<Counts>>,
+    /// The plain favicon, drawn underneath the badge once loaded.
+    icon: Option<HtmlImageElement>,
+    _listeners: Vec<EventListener>,
+}
+
+impl UnreadTracker {
+    pub fn new() -> Self {
+        let counts = Rc::new(Cell::new(Counts::default()));
+        let icon = HtmlImageElement::new().ok();
+        if let Some(icon) = &icon {
+            icon.set_src(FAVICON);
+        }
+
+        let mut listeners = vec![];
+        if let Some(window) = web_sys::window() {
+            if let Some(document) = window.document() {
+                let counts = counts.clone();
+                let target = document.clone();
+                listeners.push(EventListener::new(&target, "visibilitychange", move |_| {
+                    if !document.hidden() {
+                        reset(&counts);
+                    }
+                }));
+            }
+            let counts = counts.clone();
+            listeners.push(EventListener::new(&window, "focus", move |_| reset(&counts)));
+        }
+
+        Self {
+            counts,
+            icon,
+            _listeners: listeners,
+        }
+    }
+
+    pub fn record(&self, mention: bool) {
+        let hidden = web_sys::window()
+            .and_then(|w| w.document())
+            .is_some_and(|d| d.hidden());
+        if !hidden {
+            return;
+        }
+        let mut counts = self.counts.get();
+        counts.unread += 1;
+        if mention {
+            counts.mentions += 1;
+        }
+        self.counts.set(counts);
+        render(counts, self.icon.as_ref());
+    }
+}
+
+impl Drop for UnreadTracker {
+    fn drop(&mut self) {
+        render(Counts::default(), None);
+    }
+}
+
+fn reset(counts: &Cell<Counts>) {
+    if counts.replace(Counts::default()).unread > 0 {
+        render(Counts::default(), None);
+    }
+}
+
+fn render(counts: Counts, icon: Option<&HtmlImageElement>) {
+    let document = match web_sys::window().and_then(|w| w.document()) {
+        Some(document) => document,
+        None => return,
+    };
+    if counts.unread == 0 {
+        document.set_title(TITLE);
+    } else {
+        document.set_title(&format!("({}) {}", counts.unread, TITLE));
+    }
+
+    let href = if counts.unread == 0 {
+        Ok(FAVICON.to_string())
+    } else {
+        draw_badge(&document, counts, icon)
+    };
+    match (href, favicon_link(&document)) {
+        (Ok(href), Ok(link)) => {
+            link.set_type(if counts.unread == 0 { "image/svg+xml" } else { "image/png" });
+            link.set_href(&href);
+        }
+        (Err(e), _) | (_, Err(e)) => log::error!("failed to update favicon: {:?}", e),
+    }
+}
+
+fn favicon_link(document: &Document) -> Result<HtmlLinkElement, JsValue> {
+    if let Some(link) = document.query_selector("link[rel~='icon']")? {
+        return link.dyn_into().map_err(Into::into);
+    }
+    let link: HtmlLinkElement = document.create_element("link")?.dyn_into()?;
+    link.set_rel("icon");
+    if let Some(head) = document.head() {
+        head.append_child(&link)?;
+    }
+    Ok(link)
+}
+
+/// Draws the favicon with the unread count in a corner, as a data URL.
+fn draw_badge(
+    document: &Document,
+    counts: Counts,
+    icon: Option<&HtmlImageElement>,
+) -> Result<String, JsValue> {
+    let canvas: HtmlCanvasElement = document.create_element("canvas")?.dyn_into()?;
+    canvas.set_width(ICON_SIZE as u32);
+    canvas.set_height(ICON_SIZE as u32);
+    let context: CanvasRenderingContext2d = canvas
+        .get_context("2d")?
+        .ok_or_else(|| JsValue::from_str("no 2d context"))?
+        .dyn_into()?;
+
+    if let Some(icon) = icon.filter(|i| i.complete() && i.natural_width() > 0) {
+        context.draw_image_with_html_image_element_and_dw_and_dh(icon, 0.0, 0.0, ICON_SIZE, ICON_SIZE)?;
+    }
+
+    let radius = ICON_SIZE * 0.3;
+    let (x, y) = (ICON_SIZE - radius, ICON_SIZE - radius);
+    let color = if counts.mentions > 0 { MENTION_COLOR } else { BADGE_COLOR };
+    context.begin_path();
+    context.arc(x, y, radius, 0.0, std::f64::consts::TAU)?;
+    context.set_fill_style_str(color);
+    context.fill();
+
+    let label = if counts.unread > 9 {
+        "9+".to_string()
+    } else {
+        counts.unread.to_string()
+    };
+    context.set_fill_style_str("#ffffff");
+    context.set_font("bold 14px sans-serif");
+    context.set_text_align("center");
+    context.set_text_baseline("middle");
+    context.fill_text(&label, x, y + 1.0)?;
+
+    canvas.to_data_url()
+}
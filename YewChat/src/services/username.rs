@@ -0,0 +1,119 @@
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+use unicode_normalization::char::is_combining_mark;
+use unicode_normalization::UnicodeNormalization;
+
+pub const MIN_LEN: usize = 2;
+pub const MAX_LEN: usize = 24;
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RegisterRequest {
+    pub nick: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub resume_token: Option<String>,
+    /// Signed token from the auth service; guests send none.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub token: Option<String>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Registration {
+    pub accepted: bool,
+    /// The nickname as the server stored it, after normalization.
+    #[serde(default)]
+    pub nick: String,
+    #[serde(default)]
+    pub error: Option<String>,
+    /// Free alternatives such as `alice_2` when the nickname is taken.
+    #[serde(default)]
+    pub suggestions: Vec<String>,
+    /// Presented on the next `register` to reclaim this nickname.
+    #[serde(default)]
+    pub resume_token: Option<String>,
+}
+
+/// A nickname change, broadcast by the server. Only the requester receives
+/// refused renames, with `error` set.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Rename {
+    pub from: String,
+    pub to: String,
+    #[serde(default)]
+    pub error: Option<String>,
+}
+
+/// NFKC-normalizes and trims a nickname, so visually identical input such
+/// as full-width letters or decomposed accents maps to a single spelling.
+pub fn normalize(name: &str) -> String {
+    name.trim().nfkc().collect()
+}
+
+/// Normalizes `name` and checks it against the nickname rules, returning
+/// the normalized form or a message to show under the input.
+///
+/// The server applies the same rules, see `nickError` in `app.ts`.
+pub fn validate(name: &str) -> Result<String, String> {
+    let name = normalize(name);
+    let len = name.chars().count();
+
+    if len == 0 {
+        return Err("Enter a username".into());
+    }
+    if len < MIN_LEN {
+        return Err(format!("Use at least {} characters", MIN_LEN));
+    }
+    if len > MAX_LEN {
+        return Err(format!("Use at most {} characters", MAX_LEN));
+    }
+    if let Some(c) = name.chars().find(|c| !is_allowed(*c)) {
+        return Err(if c.is_whitespace() {
+            "Spaces aren't allowed; try _ or - instead".into()
+        } else if c.is_control() {
+            "Control characters aren't allowed".into()
+        } else {
+            format!("\"{}\" isn't allowed; use letters, digits, _, - or .", c)
+        });
+    }
+    if !name.starts_with(char::is_alphanumeric) {
+        return Err("Start with a letter or digit".into());
+    }
+    Ok(name)
+}
+
+/// Confusable skeletons of `name` as defined by Unicode TR39, so "alice"
+/// spelled with a Cyrillic "а" shares one with the Latin spelling. The
+/// lowercased form is included too, catching a capital I posing as an l.
+pub fn skeletons(name: &str) -> [String; 2] {
+    [
+        unicode_security::skeleton(name).collect(),
+        unicode_security::skeleton(&name.to_lowercase()).collect(),
+    ]
+}
+
+/// Maps each nickname that looks like an earlier one in `names` to the
+/// name it resembles. Online lists are in join order, so whoever arrived
+/// second is the one flagged.
+pub fn lookalikes(names: &[String]) -> HashMap<String, String> {
+    let mut seen: HashMap<String, &String> = HashMap::new();
+    let mut flagged = HashMap::new();
+    for name in names {
+        let keys = skeletons(name);
+        if let Some(original) = keys.iter().find_map(|k| seen.get(k)) {
+            flagged.insert(name.clone(), (*original).clone());
+            continue;
+        }
+        for key in keys {
+            seen.entry(key).or_insert(name);
+        }
+    }
+    flagged
+}
+
+// Combining marks are allowed after the first character, like the server's
+// `\p{M}`, for scripts whose marks don't compose into a single character.
+fn is_allowed(c: char) -> bool {
+    c.is_alphanumeric() || is_combining_mark(c) || c == '_' || c == '-' || c == '.'
+}
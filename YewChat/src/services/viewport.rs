@@ -0,0 +1,164 @@
+use gloo::events::EventListener;
+use web_sys::Window;
+use yew::Callback;
+
+/// Shrinkage of the visual viewport, in pixels, taken to mean the on-screen
+/// keyboard is up rather than a browser toolbar sliding in.
+const KEYBOARD_MIN_PX: f64 = 120.0;
+
+/// The height left above the keyboard, given the window's full height and the
+/// visual viewport's, or `None` when the keyboard is down.
+pub fn keyboard_height(full: f64, visible: f64) -> Option<f64> {
+    (full - visible >= KEYBOARD_MIN_PX).then_some(visible)
+}
+
+/// Inline style that shrinks the chat to fit above the keyboard.
+pub fn keyboard_style(height: Option<f64>) -> Option<String> {
+    height.map(|height| format!("height: {}px", height))
+}
+
+/// Reports the height left above the on-screen keyboard while it is shown,
+/// and `None` once it is gone. Does nothing on browsers without the visual
+/// viewport API. Dropping it stops watching.
+pub struct KeyboardWatcher {
+    _listeners: Vec<EventListener>,
+}
+
+impl KeyboardWatcher {
+    pub fn new(onchange: Callback<Option<f64>>) -> Self {
+        match web_sys::window() {
+            Some(window) => Self::watch(&window, onchange),
+            None => Self { _listeners: vec![] },
+        }
+    }
+
+    fn watch(window: &Window, onchange: Callback<Option<f64>>) -> Self {
+        let viewport = match window.visual_viewport() {
+            Some(viewport) => viewport,
+            None => return Self { _listeners: vec![] },
+        };
+
+        let mut listeners = vec![];
+        for event in ["resize", "scroll"] {
+            let window = window.clone();
+            let visible = viewport.clone();
+            let onchange = onchange.clone();
+            listeners.push(EventListener::new(&viewport, event, move |_| {
+                let full = window
+                    .inner_height()
+                    .ok()
+                    .and_then(|h| h.as_f64())
+                    .unwrap_or_default();
+                onchange.emit(keyboard_height(full, visible.height()));
+            }));
+        }
+
+        Self {
+            _listeners: listeners,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn toolbars_are_not_keyboards() {
+        assert_eq!(keyboard_height(800.0, 800.0), None);
+        assert_eq!(keyboard_height(800.0, 740.0), None);
+        assert_eq!(keyboard_height(800.0, 680.0), Some(680.0));
+        assert_eq!(keyboard_height(800.0, 420.0), Some(420.0));
+    }
+
+    #[test]
+    fn style_follows_the_keyboard() {
+        assert_eq!(keyboard_style(None), None);
+        assert_eq!(keyboard_style(Some(420.0)).as_deref(), Some("height: 420px"));
+    }
+}
+
+#[cfg(all(test, target_arch = "wasm32"))]
+mod browser_tests {
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    use wasm_bindgen_test::*;
+    use web_sys::{Event, HtmlIFrameElement};
+    use yew::prelude::*;
+
+    use super::*;
+    use crate::test_support::{query, render_in, settle, viewport};
+
+    wasm_bindgen_test_configure!(run_in_browser);
+
+    const STYLES: &str = "
+        .flex { display: flex; }
+        .flex-col { flex-direction: column; }
+        .flex-1 { flex: 1 1 0%; }
+        .h-screen { height: 100vh; }
+        .h-16 { height: 4rem; }
+    ";
+
+    #[derive(Properties, PartialEq)]
+    struct LayoutProps {
+        #[prop_or_default]
+        keyboard: Option<f64>,
+    }
+
+    /// The chat's outer column: timeline on top, composer pinned below it.
+    #[function_component(Layout)]
+    fn layout(props: &LayoutProps) -> Html {
+        let style = keyboard_style(props.keyboard);
+        html! {
+            <div class="flex flex-col h-screen" {style}>
+                <div class="flex-1"></div>
+                <div class="composer h-16"></div>
+            </div>
+        }
+    }
+
+    async fn composer_bottom(iframe: &HtmlIFrameElement, keyboard: Option<f64>) -> f64 {
+        let root = render_in::<Layout>(iframe, LayoutProps { keyboard }).await;
+        let bottom = query(&root, ".composer")
+            .unwrap()
+            .get_bounding_client_rect()
+            .bottom();
+        root.remove();
+        bottom
+    }
+
+    #[wasm_bindgen_test]
+    async fn narrow_composer_sits_above_the_keyboard() {
+        let iframe = viewport(375, STYLES).await;
+        assert_eq!(composer_bottom(&iframe, None).await, 600.0);
+        assert_eq!(composer_bottom(&iframe, Some(300.0)).await, 300.0);
+    }
+
+    #[wasm_bindgen_test]
+    async fn wide_composer_sits_above_the_keyboard() {
+        let iframe = viewport(1024, STYLES).await;
+        assert_eq!(composer_bottom(&iframe, None).await, 600.0);
+        assert_eq!(composer_bottom(&iframe, Some(300.0)).await, 300.0);
+    }
+
+    #[wasm_bindgen_test]
+    async fn watcher_reports_no_keyboard_on_a_full_viewport() {
+        let iframe = viewport(375, STYLES).await;
+        let window = iframe.content_window().unwrap();
+        let seen = Rc::new(RefCell::new(vec![]));
+        let _watcher = KeyboardWatcher::watch(&window, {
+            let seen = seen.clone();
+            Callback::from(move |height| seen.borrow_mut().push(height))
+        });
+
+        let resize = Event::new("resize").unwrap();
+        window
+            .visual_viewport()
+            .unwrap()
+            .dispatch_event(&resize)
+            .unwrap();
+        settle().await;
+        assert_eq!(*seen.borrow(), vec![None]);
+    }
+}
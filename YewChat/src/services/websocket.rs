@@ -1,13 +1,15 @@
-use futures::{channel::mpsc::Sender, SinkExt, StreamExt};
+use futures::{channel::mpsc::Sender, stream, SinkExt, StreamExt};
 use reqwasm::websocket::{futures::WebSocket, Message};
 
 use wasm_bindgen_futures::spawn_local;
 use yew_agent::Dispatched;
 
 use crate::services::event_bus::{EventBus, Request};
+use crate::services::file_transfer;
 
 pub struct WebsocketService {
     pub tx: Sender<String>,
+    pub bytes_tx: Sender<Vec<u8>>,
 }
 
 impl WebsocketService {
@@ -16,13 +18,30 @@ impl WebsocketService {
 
         let (mut write, mut read) = ws.split();
 
-        let (in_tx, mut in_rx) = futures::channel::mpsc::channel::<String>(1000);
+        let (in_tx, in_rx) = futures::channel::mpsc::channel::<String>(1000);
+        // Kept small so file senders are paced by the socket instead of
+        // queueing a whole file in memory.
+        let (bytes_tx, bytes_rx) = futures::channel::mpsc::channel::<Vec<u8>>(4);
         let mut event_bus = EventBus::dispatcher();
 
         spawn_local(async move {
-            while let Some(s) = in_rx.next().await {
-                log::debug!("got event from channel! {}", s);
-                write.send(Message::Text(s)).await.unwrap();
+            let mut outgoing = stream::select(
+                in_rx.map(Message::Text),
+                bytes_rx.map(Message::Bytes),
+            );
+            while let Some(msg) = outgoing.next().await {
+                if let Message::Text(s) = &msg {
+                    log::debug!("got event from channel! {}", s);
+                }
+                if let Err(e) = write.send(msg).await {
+                    log::error!("ws write: {:?}", e);
+                    break;
+                }
+            }
+            // Every sender is gone or closed, so say goodbye properly instead
+            // of leaving the server to notice the dead connection.
+            if let Err(e) = write.close().await {
+                log::error!("ws close: {:?}", e);
             }
         });
 
@@ -34,6 +53,10 @@ impl WebsocketService {
                         event_bus.send(Request::EventBusMsg(data));
                     }
                     Ok(Message::Bytes(b)) => {
+                        if file_transfer::is_chunk_frame(&b) {
+                            event_bus.send(Request::Bytes(b));
+                            continue;
+                        }
                         let decoded = std::str::from_utf8(&b);
                         if let Ok(val) = decoded {
                             log::debug!("from websocket: {}", val);
@@ -46,8 +69,18 @@ impl WebsocketService {
                 }
             }
             log::debug!("WebSocket Closed");
+            event_bus.send(Request::Closed);
         });
 
-        Self { tx: in_tx }
+        Self {
+            tx: in_tx,
+            bytes_tx,
+        }
     }
-}
\ No newline at end of file
+
+    /// Closes the socket once everything already queued has been written.
+    pub fn close(&mut self) {
+        self.tx.close_channel();
+        self.bytes_tx.close_channel();
+    }
+}
@@ -0,0 +1,74 @@
+use futures::channel::oneshot;
+use gloo::events::EventListener;
+use gloo::timers::future::TimeoutFuture;
+use wasm_bindgen::JsCast;
+use web_sys::{Document, Element, HtmlElement, HtmlIFrameElement};
+use yew::Component;
+
+pub fn document() -> Document {
+    web_sys::window().unwrap().document().unwrap()
+}
+
+pub fn query(root: &Element, selector: &str) -> Option<HtmlElement> {
+    root.query_selector(selector)
+        .unwrap()
+        .map(|e| e.unchecked_into())
+}
+
+/// A `width` by 600 pixel iframe with `styles`, so media queries see a
+/// viewport of that size. The app loads the real Tailwind from a CDN, so
+/// tests pass just the rules they rely on.
+pub async fn viewport(width: u32, styles: &str) -> HtmlIFrameElement {
+    let iframe: HtmlIFrameElement = document().create_element("iframe").unwrap().unchecked_into();
+    iframe
+        .set_attribute("style", &format!("width: {}px; height: 600px; border: 0", width))
+        .unwrap();
+    iframe
+        .set_attribute(
+            "srcdoc",
+            &format!("<!DOCTYPE html><style>body {{ margin: 0 }} {}</style><body></body>", styles),
+        )
+        .unwrap();
+    let (loaded, wait) = oneshot::channel();
+    let _listener = EventListener::once(&iframe, "load", move |_| {
+        let _ = loaded.send(());
+    });
+    document().body().unwrap().append_child(&iframe).unwrap();
+    wait.await.unwrap();
+    iframe
+}
+
+/// Renders `COMP` into a fresh element of the iframe's body. Yew listens for
+/// events on the top document, so clicks have to be tested outside iframes.
+pub async fn render_in<COMP: Component>(iframe: &HtmlIFrameElement, props: COMP::Properties) -> Element {
+    let frame = iframe.content_document().unwrap();
+    let root = frame.create_element("div").unwrap();
+    frame.body().unwrap().append_child(&root).unwrap();
+    yew::start_app_with_props_in_element::<COMP>(root.clone(), props);
+    settle().await;
+    root
+}
+
+/// Renders `COMP` into the top document, where its event handlers work.
+pub async fn render<COMP: Component>(props: COMP::Properties) -> Element {
+    let root = document().create_element("div").unwrap();
+    document().body().unwrap().append_child(&root).unwrap();
+    yew::start_app_with_props_in_element::<COMP>(root.clone(), props);
+    settle().await;
+    root
+}
+
+/// Lets pending renders finish.
+pub async fn settle() {
+    TimeoutFuture::new(0).await;
+}
+
+pub fn is_displayed(iframe: &HtmlIFrameElement, element: &Element) -> bool {
+    let style = iframe
+        .content_window()
+        .unwrap()
+        .get_computed_style(element)
+        .unwrap()
+        .unwrap();
+    style.get_property_value("display").unwrap() != "none"
+}